@@ -0,0 +1,54 @@
+use anyhow::bail;
+
+/// A quick-stake amount, either a percentage of the balance or a fixed sum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Amount {
+    Percent(u64),
+    Fixed(u64)
+}
+
+impl Amount {
+    pub fn of(&self, balance: u64) -> u64 {
+        match self {
+            Amount::Percent(percent) => balance * percent / 100,
+            Amount::Fixed(amount) => *amount
+        }
+    }
+
+    pub fn label(&self, currency: &str) -> String {
+        match self {
+            Amount::Percent(100) => "All in".to_string(),
+            Amount::Percent(percent) => format!("{}%", percent),
+            Amount::Fixed(amount) => format!("{} {}", amount, currency)
+        }
+    }
+}
+
+impl ToString for Amount {
+    fn to_string(&self) -> String {
+        match self {
+            Amount::Percent(percent) => format!("{}%", percent),
+            Amount::Fixed(amount) => format!("{}", amount)
+        }
+    }
+}
+
+impl TryFrom<&str> for Amount {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(if let Some(percent) = value.trim().strip_suffix("%") {
+            let percent: u64 = percent.trim().parse()?;
+            if percent == 0 || percent > 100 {
+                bail!("'{}' is not a valid percentage, expecting 1% to 100%", value);
+            }
+            Amount::Percent(percent)
+        } else {
+            let amount: u64 = value.trim().parse()?;
+            if amount == 0 {
+                bail!("amount must be positive");
+            }
+            Amount::Fixed(amount)
+        })
+    }
+}
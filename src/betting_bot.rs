@@ -1,29 +1,39 @@
-use std::sync::atomic::AtomicBool;
+use std::{collections::HashMap, sync::{atomic::AtomicBool, Arc, Mutex}};
 use betting::{Bets, BetError, AccountStatus};
 use db_map::DBMap;
 use anyhow::{Result, Ok};
-use crate::{serialize_utils::BetOutcome, config::config};
+use log::warn;
+use serenity::http::Http;
+use crate::{serialize_utils::BetOutcome, bot_db::{BotDB, TxKind}, leaderboard::leaderboard_refresh, render::Renders};
 
+// cheap to clone so that scheduled tasks can hold on to the bot
+#[derive(Clone)]
 pub struct BettingBot {
     pub bets: Bets,
-    pub msg_map: DBMap<BetOutcome, u64>,
-    pub is_loop_running: AtomicBool
+    pub msg_map: Arc<DBMap<BetOutcome, u64>>,
+    pub bot_db: BotDB,
+    pub is_loop_running: Arc<AtomicBool>,
+    pub renders: Arc<Mutex<Renders>>
 }
 
 impl BettingBot {
     pub fn new() -> Self {
         BettingBot { 
             bets: Bets::new("bets.db").unwrap(), 
-            msg_map: DBMap::new("msg_map.db").unwrap(),
-            is_loop_running: AtomicBool::new(false)
+            msg_map: Arc::new(DBMap::new("msg_map.db").unwrap()),
+            bot_db: BotDB::new("bot.db").unwrap(),
+            is_loop_running: Arc::new(AtomicBool::new(false)),
+            renders: Arc::new(Mutex::new(Renders::default()))
         }
     }
 
     pub fn balance_create(&self, server: u64, user: u64) -> Result<u64> {
         Ok(match self.bets.balance(server, user) {
             Err(BetError::NotFound) => {
-                self.bets.create_account(server, user, config.starting_coins as u64)?;
-                config.starting_coins as u64
+                let starting_coins = self.bot_db.guild_config(server)?.starting_coins as u64;
+                self.bets.create_account(server, user, starting_coins)?;
+                self.bot_db.add_transaction(server, user, starting_coins as i64, TxKind::Account, None)?;
+                starting_coins
             },
             res => res?
         })
@@ -32,10 +42,71 @@ impl BettingBot {
     pub fn account_create(&self, server: u64, user: u64) -> Result<AccountStatus> {
         Ok(match self.bets.account(server, user) {
             Err(BetError::NotFound) => {
-                self.bets.create_account(server, user, config.starting_coins as u64)?;
-                AccountStatus { user, balance: config.starting_coins as u64, in_bet: 0 }
+                let starting_coins = self.bot_db.guild_config(server)?.starting_coins as u64;
+                self.bets.create_account(server, user, starting_coins)?;
+                self.bot_db.add_transaction(server, user, starting_coins as i64, TxKind::Account, None)?;
+                AccountStatus { user, balance: starting_coins, in_bet: 0 }
             },
             res => res?
         })
     }
+
+    /// Refreshes the permanent leaderboard of the server, errors are only logged
+    pub async fn leaderboard_update(&self, http: &Http, server: u64) {
+        if let Err(why) = leaderboard_refresh(http, &self.bets, &self.bot_db, server).await {
+            warn!(target: "betting-bot", "couldn't update the leaderboard of {}: {:?}", server, why);
+        }
+    }
+
+    /// Credits (or debits if negative) the account, creating it first if needed
+    pub fn balance_add(&self, server: u64, user: u64, diff: i64, kind: TxKind) -> Result<u64> {
+        self.balance_create(server, user)?;
+        let balance = self.bets.change_balance(server, user, diff)?;
+        self.bot_db.add_transaction(server, user, diff, kind, None)?;
+        Ok(balance)
+    }
+
+    /// Runs an operation of the `betting` crate and records the balance change it reports for each user
+    pub fn ledgered<T>(
+        &self, server: u64, kind: TxKind, bet: Option<u64>, op: impl FnOnce() -> Result<(T, HashMap<u64, i64>)>
+    ) -> Result<T> {
+        let (res, diffs) = op()?;
+        for (user, diff) in diffs {
+            if diff != 0 {
+                self.bot_db.add_transaction(server, user, diff, kind, bet)?;
+            }
+        }
+        Ok(res)
+    }
+
+    /// The wagers of the bet as (outcome, user, amount), 
+    /// along with the stakes the `betting` crate holds from before the bot kept track of them
+    pub fn wagers(&self, server: u64, bet: u64) -> Result<Vec<(usize, u64, u64)>> {
+        let mut wagers = self.bot_db.wagers(bet)?;
+        for account in self.bets.accounts(server)? {
+            match self.bets.position(account.user, bet) {
+                Err(BetError::NotFound) => {},
+                position => {
+                    let position = position?;
+                    let tracked: u64 = wagers.iter()
+                        .filter(|(outcome, user, _)| *outcome == position.outcome && *user == account.user)
+                        .map(|(_, _, amount)| amount).sum();
+                    if position.amount > tracked {
+                        wagers.push((position.outcome, account.user, position.amount - tracked));
+                    }
+                }
+            }
+        }
+        Ok(wagers)
+    }
+
+    /// Aborts the bet in the `betting` crate and returns the stakes each user got back
+    pub fn abort_refunds(&self, server: u64, bet: u64) -> Result<HashMap<u64, i64>> {
+        let mut refunds: HashMap<u64, i64> = HashMap::new();
+        for (_, user, amount) in self.wagers(server, bet)? {
+            *refunds.entry(user).or_default() += amount as i64;
+        }
+        self.bets.abort_bet(bet)?;
+        Ok(refunds)
+    }
 }
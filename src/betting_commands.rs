@@ -1,34 +1,275 @@
+use std::collections::HashMap;
 use anyhow::{Result, bail, Ok, anyhow};
-use chrono::prelude::*;
-use itertools::Itertools;
+use chrono::{prelude::*, Duration};
 use serenity::{
     all::{
         CommandInteraction, CommandOptionType, CreateActionRow, 
-        CreateButton, CreateCommand, CreateCommandOption, 
+        CreateAllowedMentions, CreateButton, CreateCommand, CreateCommandOption, 
         CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, 
-        CreateModal, EditMessage
+        CreateModal, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, Permissions
     }, 
     http::Http, model::{
         application::{
-            ActionRowComponent, ButtonStyle, ComponentInteraction, 
+            ActionRowComponent, ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, 
             InputTextStyle, InteractionResponseFlags, ModalInteraction
         }, 
-        prelude::{CommandDataOptionValue, GuildId}
+        channel::MessageFlags, 
+        prelude::{ChannelId, CommandDataOptionValue, GuildId, Member}
     }, prelude::*
 };
 use serenity_utils::{BotUtil, MessageBuilder, Button, CommandUtil};
 use shellwords::split;
-use crate::{betting_bot::BettingBot, config::config, serialize_utils::{BetOutcome, BetAction}, front_utils::{shorten, outcomes_display, bet_stub}};
+use itertools::Itertools;
+use betting::{Bet, utils::lrm};
+use log::{info, warn};
+use crate::{
+    betting_bot::BettingBot, config::{Config, GUILD_KEYS}, serialize_utils::{BetOutcome, BetAction}, 
+    front_utils::{
+        shorten, outcomes_display, bet_stub, outcome_buttons, bet_header, positions_display, 
+        bet_embed, compact_bet_components, fixed_odds_line, MAX_COMPACT_OUTCOMES
+    }, 
+    bookmaker::FixedOdds, policy::{AuthorPolicy, BetPolicy, NoWinnerPolicy}, 
+    bot_db::{BetEntry, BetStatus, TxKind}, amount::Amount, stats::Stats, resync::is_not_found, 
+    leaderboard::{PERMANENT_HEADER, PAGE_SIZE, LeaderboardSort, leaderboard_display, leaderboard_components, page_count}
+};
+
+fn option_value<'a>(command: &'a CommandInteraction, name: &str) -> Option<&'a CommandDataOptionValue> {
+    command.data.options.iter().find(|option| option.name == name).map(|option| &option.value)
+}
+
+fn has_role(member: &Member, role: Option<u64>) -> bool {
+    member.permissions.is_some_and(|permissions| permissions.administrator())
+        || role.is_some_and(|role| member.roles.iter().any(|role_id| role_id.get() == role))
+}
+
+fn is_admin(member: Option<&Member>) -> bool {
+    member.is_some_and(|member| has_role(member, None))
+}
+
+fn option_str(command: &CommandInteraction, name: &str) -> Option<String> {
+    match option_value(command, name) {
+        Some(CommandDataOptionValue::String(value)) => Some(value.clone()),
+        _ => None
+    }
+}
+
+fn pool_nets(wagers: &[(usize, u64, u64)], winners: &[usize], rake: u32, jackpot: u64) -> (u64, HashMap<u64, i64>) {
+    let pool: u64 = wagers.iter().map(|(_, _, amount)| amount).sum();
+    let raked = pool * rake as u64 / 100;
+    let winning: Vec<&(usize, u64, u64)> = wagers.iter().filter(|(outcome, _, _)| winners.contains(outcome)).collect();
+    let shares = if winning.is_empty() { 
+        Vec::new() 
+    } else { 
+        lrm(pool - raked + jackpot, &winning.iter().map(|(_, _, amount)| *amount).collect::<Vec<u64>>()) 
+    };
+    let mut nets: HashMap<u64, i64> = HashMap::new();
+    for (_, user, amount) in wagers {
+        *nets.entry(*user).or_default() -= *amount as i64;
+    }
+    for ((_, user, _), share) in winning.iter().zip(shares) {
+        *nets.entry(*user).or_default() += share as i64;
+    }
+    (raked, nets)
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let too_long = || anyhow!("'{}' is too long for a duration", value);
+    if let Result::Ok(minutes) = value.parse::<i64>() {
+        return Duration::try_minutes(minutes).ok_or_else(too_long);
+    }
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| anyhow!("'{}' is not a duration", value))?;
+        let part = match c {
+            'd' => Duration::try_days(n),
+            'h' => Duration::try_hours(n),
+            'm' => Duration::try_minutes(n),
+            's' => Duration::try_seconds(n),
+            _ => bail!("'{}' is not a duration, expecting something like 1h30m", value)
+        };
+        total = part.and_then(|part| total.checked_add(&part)).ok_or_else(too_long)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        bail!("'{}' is missing a unit at the end", value);
+    }
+    Ok(total)
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Local>> {
+    let value = value.trim();
+    let naive = if let Result::Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        let now = Local::now();
+        let mut naive = now.date_naive().and_time(time);
+        if naive <= now.naive_local() {
+            naive = naive + Duration::days(1);
+        }
+        naive
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+            .map_err(|_| anyhow!("'{}' is not a time, expecting HH:MM or YYYY-MM-DD HH:MM", value))?
+    };
+    Local.from_local_datetime(&naive).earliest().ok_or(anyhow!("'{}' doesn't exist in local time", value))
+}
+
+pub fn open_bet_buttons() -> Vec<Button> {
+    vec![
+        Button { custom_id: BetAction::Lock().to_string(), label: "🔒 Lock".to_string(), style: ButtonStyle::Secondary },
+        Button { custom_id: BetAction::Abort().to_string(), label: "🚫 Abort".to_string(), style: ButtonStyle::Secondary }
+    ]
+}
+
+pub fn resolve_components(outcome: BetOutcome) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(BetAction::Resolve(outcome).to_string())
+            .label("🏆 Resolve".to_string())
+            .style(ButtonStyle::Secondary)
+    ])]
+}
+
+pub fn locked_bet_components(outcomes: &[String]) -> Vec<CreateActionRow> {
+    // select menus hold 25 options at most
+    let options: Vec<CreateSelectMenuOption> = outcomes.iter().take(25).enumerate().map(|(i, desc)| 
+        CreateSelectMenuOption::new(format!("#{} {}", i + 1, shorten(desc, 90)), i.to_string())
+    ).collect();
+    vec![
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(BetAction::ResolveSelect().to_string(), CreateSelectMenuKind::String { options })
+                .placeholder("🏆 Pick the winners")
+                .min_values(1)
+                .max_values(outcomes.len().min(25) as u8)
+        ),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::Abort().to_string()).label("🚫 Abort".to_string()).style(ButtonStyle::Secondary)
+        ])
+    ]
+}
 
 impl BettingBot {
     pub async fn account_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
         let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
         let user_uuid = command.user.id.get();
         let account: betting::AccountStatus = self.account_create(server_uuid, user_uuid)?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let mut msg = format!(
+            "Balance: {} {} | In bet: {} {}", account.balance, cfg.currency, account.in_bet, cfg.currency
+        );
+        let transfers = self.bot_db.transfers(server_uuid, user_uuid, 5)?;
+        if !transfers.is_empty() {
+            msg += "\n**Recent transfers**";
+            for transfer in transfers {
+                let (sign, other) = if transfer.sender == user_uuid { 
+                    ("-", format!("to <@{}>", transfer.receiver)) 
+                } else { 
+                    ("+", format!("from <@{}>", transfer.sender)) 
+                };
+                let note = transfer.note.map(|note| format!(" · {}", shorten(&note, 40))).unwrap_or_default();
+                msg += &format!(
+                    "\n`{}{}` {} {}{}  <t:{}:R>", sign, transfer.amount, cfg.currency, other, note, transfer.time
+                );
+            }
+        }
         command.response(
-            &ctx.http, MessageBuilder::new(format!(
-            "Balance: {} {} | In bet: {} {}", account.balance, config.currency, account.in_bet, config.currency
-            )).ephemeral(true), InteractionResponseFlags::EPHEMERAL
+            &ctx.http, MessageBuilder::new(msg).ephemeral(true), InteractionResponseFlags::EPHEMERAL
+        ).await?;
+        Ok(())
+    }
+
+    fn give_check(&self, server: u64, sender: u64, receiver: u64, amount: u64, cfg: &Config) -> Result<Option<String>> {
+        if sender == receiver {
+            return Ok(Some("You can't give coins to yourself".to_string()));
+        }
+        let balance = self.balance_create(server, sender)?;
+        if amount > balance {
+            return Ok(Some(format!("You can't give {} {} with a balance of {} {}", amount, cfg.currency, balance, cfg.currency)));
+        }
+        if cfg.give_cap > 0 {
+            let sent = self.bot_db.sent_since(server, sender, Utc::now().timestamp() - 24*3600)?;
+            if sent + amount > cfg.give_cap as u64 {
+                return Ok(Some(format!(
+                    "You can give {} {} per day, you have {} {} left", 
+                    cfg.give_cap, cfg.currency, (cfg.give_cap as u64).saturating_sub(sent), cfg.currency
+                )));
+            }
+        }
+        Ok(None)
+    }
+
+    fn transfer(&self, server: u64, sender: u64, receiver: u64, amount: u64, note: Option<&str>, cfg: &Config) -> Result<String> {
+        self.balance_create(server, receiver)?;
+        self.balance_add(server, sender, -(amount as i64), TxKind::Transfer)?;
+        self.balance_add(server, receiver, amount as i64, TxKind::Transfer)?;
+        self.bot_db.add_transfer(server, sender, receiver, amount, note)?;
+        let note = note.map(|note| format!("\n> {}", note)).unwrap_or_default();
+        Ok(format!("💸 <@{}> gave {} {} to <@{}>{}", sender, amount, cfg.currency, receiver, note))
+    }
+
+    pub async fn give_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let sender = command.user.id.get();
+        let (receiver, amount) = match (option_value(&command, "user"), option_value(&command, "amount")) {
+            (Some(CommandDataOptionValue::User(user_id)), Some(CommandDataOptionValue::Integer(amount))) => (user_id.get(), *amount as u64),
+            _ => bail!("expected a user and an amount")
+        };
+        let note = option_str(&command, "note");
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        if let Some(why) = self.give_check(server_uuid, sender, receiver, amount, &cfg)? {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(why.clone()).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!(why);
+        }
+        if cfg.give_confirm > 0 && amount >= cfg.give_confirm as u64 {
+            let pending_id = self.bot_db.set_pending_transfer(server_uuid, sender, receiver, amount, note.as_deref())?;
+            command.response(
+                &ctx.http,
+                MessageBuilder::new(format!("Give {} {} to <@{}>?", amount, cfg.currency, receiver)).buttons(vec![
+                    Button { custom_id: BetAction::GiveCancel(pending_id).to_string(), label: "Cancel".to_string(), style: ButtonStyle::Secondary },
+                    Button { custom_id: BetAction::Give(pending_id).to_string(), label: "Give".to_string(), style: ButtonStyle::Success }
+                ]).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            return Ok(());
+        }
+        let msg = self.transfer(server_uuid, sender, receiver, amount, note.as_deref(), &cfg)?;
+        command.response(&ctx.http, MessageBuilder::new(msg), InteractionResponseFlags::default()).await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
+        Ok(())
+    }
+
+    pub async fn give_action(&self, ctx: Context, command: &ComponentInteraction, pending_id: u64, confirmed: bool) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let sender = command.user.id.get();
+        let pending = self.bot_db.take_pending_transfer(server_uuid, sender, pending_id)?;
+        let content = match (pending, confirmed) {
+            (None, _) => "*This transfer is no longer pending.*".to_string(),
+            (Some(_), false) => "*Transfer cancelled.*".to_string(),
+            (Some((receiver, amount, note)), true) => {
+                let cfg = self.bot_db.guild_config(server_uuid)?;
+                match self.give_check(server_uuid, sender, receiver, amount, &cfg)? {
+                    Some(why) => format!("*{}*", why),
+                    None => {
+                        let msg = self.transfer(server_uuid, sender, receiver, amount, note.as_deref(), &cfg)?;
+                        ctx.http.send(command.channel_id, MessageBuilder::new(msg)).await?;
+                        self.leaderboard_update(&ctx.http, server_uuid).await;
+                        "*Sent.*".to_string()
+                    }
+                }
+            }
+        };
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(content).components(vec![])
+            )
         ).await?;
         Ok(())
     }
@@ -61,12 +302,68 @@ impl BettingBot {
         Ok((desc, outcomes))
     }
 
+    fn lock_parse(command: &CommandInteraction) -> Result<Option<DateTime<Local>>> {
+        let now = Local::now();
+        let lock_at = match (option_str(command, "lock_in"), option_str(command, "lock_at")) {
+            (Some(_), Some(_)) => bail!("use either lock_in or lock_at, not both"),
+            (Some(lock_in), None) => Some(
+                now.checked_add_signed(parse_duration(&lock_in)?).ok_or(anyhow!("'{}' is too far away", lock_in))?
+            ),
+            (None, Some(lock_at)) => Some(parse_time(&lock_at)?),
+            (None, None) => None
+        };
+        if lock_at.is_some_and(|time| time < now + Duration::minutes(1)) {
+            bail!("the bet must stay open for at least a minute");
+        }
+        Ok(lock_at)
+    }
+
+    fn odds_parse(&self, command: &CommandInteraction, server: u64, outcomes: usize) -> Result<Option<FixedOdds>> {
+        let liability = match option_value(command, "liability") {
+            Some(CommandDataOptionValue::Integer(liability)) => Some(*liability as u64),
+            _ => None
+        };
+        match (option_str(command, "odds"), liability) {
+            (None, None) => Ok(None),
+            (Some(odds), Some(liability)) => {
+                let fixed_odds = FixedOdds::parse(&odds, outcomes, liability)?;
+                let balance = self.balance_create(server, command.user.id.get())?;
+                if liability > balance {
+                    bail!("you only have {} to back the bet", balance);
+                }
+                Ok(Some(fixed_odds))
+            },
+            _ => bail!("a fixed-odds bet needs both the odds and the liability")
+        }
+    }
+
     pub async fn bet_command(
         &self,
         ctx: Context,
         command: CommandInteraction,
     ) -> Result<()> {
         let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?;
+        let channels = self.bot_db.betting_channels(server_uuid.get())?;
+        if !channels.is_empty() && !channels.contains(&command.channel_id.get()) {
+            command.response(
+                &ctx.http,
+                MessageBuilder::new(format!(
+                    "Bets are made in {}", channels.iter().map(|channel| format!("<#{}>", channel)).join(", ")
+                )).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("not a betting channel");
+        }
+        if let Some(role) = self.bot_db.guild_config(server_uuid.get())?.creator_role {
+            if !command.member.as_ref().is_some_and(|member| has_role(member, Some(role))) {
+                command.response(
+                    &ctx.http,
+                    MessageBuilder::new(format!("Creating bets requires the <@&{}> role", role)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("user doesn't have the bet creator role");
+            }
+        }
         let (desc, outcomes) = Self::bet_parse(&command)?;
         if outcomes.len() < 2 {
             command.response(
@@ -77,209 +374,1221 @@ impl BettingBot {
             .await?;
             bail!("Less than 2 ouctomes");
         }
-        let bet_msg = command.response(
+        let lock_at = match Self::lock_parse(&command) {
+            Result::Ok(lock_at) => lock_at.map(|time| time.timestamp()),
+            Err(why) => {
+                command.response(
+                    &ctx.http,
+                    MessageBuilder::new(format!("Invalid lock time: {}", why)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!(why);
+            }
+        };
+        let cfg = self.bot_db.guild_config(server_uuid.get())?;
+        let policy = match option_str(&command, "policy") {
+            Some(policy) => BetPolicy::try_from(policy.as_str())?,
+            None => cfg.bet_policy
+        };
+        let fixed_odds = match self.odds_parse(&command, server_uuid.get(), outcomes.len()) {
+            Result::Ok(fixed_odds) => fixed_odds,
+            Err(why) => {
+                command.response(
+                    &ctx.http,
+                    MessageBuilder::new(format!("Invalid fixed odds: {}", why)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!(why);
+            }
+        };
+        let mut header = bet_header(&desc, lock_at, policy);
+        if let Some(fixed_odds) = &fixed_odds {
+            header += &fixed_odds_line(command.user.id.get(), fixed_odds, &cfg.currency);
+        }
+        let compact = match option_value(&command, "compact") {
+            Some(CommandDataOptionValue::Boolean(compact)) => *compact,
+            _ => cfg.compact
+        } && outcomes.len() <= MAX_COMPACT_OUTCOMES;
+        let mut bet_msg = command.response(
             &ctx.http, 
-            MessageBuilder::new(&desc).buttons(vec![
-                Button { custom_id: BetAction::Lock().to_string(), label: "🔒 Lock".to_string(), style: ButtonStyle::Secondary },
-                Button { custom_id: BetAction::Abort().to_string(), label: "🚫 Abort".to_string(), style: ButtonStyle::Secondary }
-            ]),
+            MessageBuilder::new(header).buttons(open_bet_buttons()),
             InteractionResponseFlags::default()
         ).await?;
         let bet_uuid = bet_msg.id.get();
         self.bets.create_bet(bet_uuid, server_uuid.get(), command.user.id.get(), desc, &outcomes)?;
-        let outcome_displays = outcomes_display(&bet_stub(&outcomes));
-        for (i, outcome) in outcome_displays.iter().enumerate() {
-            let outcome_msg = ctx.http.send(bet_msg.channel_id, MessageBuilder::new(outcome).buttons(vec![
-                Button { 
-                    custom_id: BetAction::BetClick(BetOutcome { bet_id: bet_uuid, outcome_id: i }).to_string(), 
-                    label: format!("{} Bet", config.currency), style: ButtonStyle::Primary 
-                }
-            ])).await?;
-            self.msg_map.insert(BetOutcome {bet_id: bet_uuid, outcome_id: i}, outcome_msg.id.get())?;
+        self.bot_db.add_bet(bet_uuid, server_uuid.get(), bet_msg.channel_id.get(), command.user.id.get())?;
+        self.bot_db.add_outcomes(bet_uuid, &outcomes)?;
+        self.bot_db.set_policy(bet_uuid, policy)?;
+        if let Some(fixed_odds) = &fixed_odds {
+            self.bets.change_balance(server_uuid.get(), command.user.id.get(), -(fixed_odds.escrow as i64))?;
+            self.bot_db.add_transaction(
+                server_uuid.get(), command.user.id.get(), -(fixed_odds.escrow as i64), TxKind::Escrow, Some(bet_uuid)
+            )?;
+            self.bot_db.set_fixed_odds(bet_uuid, fixed_odds)?;
+        }
+        if compact {
+            self.bot_db.set_compact(bet_uuid)?;
+            // the buttons need the id of the message so they're only added now
+            bet_msg.edit(&ctx.http, EditMessage::new()
+                .embed(bet_embed(&bet_stub(&outcomes), &cfg, fixed_odds.as_ref(), &[]))
+                .components(compact_bet_components(bet_uuid, &outcomes))
+            ).await?;
+        } else {
+            let outcome_displays = outcomes_display(&bet_stub(&outcomes), &cfg, fixed_odds.as_ref());
+            for (i, outcome) in outcome_displays.iter().enumerate() {
+                let outcome_msg = ctx.http.send(bet_msg.channel_id, MessageBuilder::new(outcome).buttons(
+                    outcome_buttons(&BetOutcome { bet_id: bet_uuid, outcome_id: i }, &cfg)
+                )).await?;
+                self.msg_map.insert(BetOutcome {bet_id: bet_uuid, outcome_id: i}, outcome_msg.id.get())?;
+            }
+        }
+        if let Some(lock_at) = lock_at {
+            self.bot_db.set_auto_lock(bet_uuid, lock_at)?;
+            self.schedule_lock(ctx.http.clone(), bet_uuid, lock_at);
         }
         Ok(())
     }
 
-    pub async fn leaderboard_command(
-        &self,
-        ctx: Context,
-        command: CommandInteraction,
-    ) -> Result<()> {
-        let guild_id = command.guild_id.ok_or(anyhow!("command used outside a server"))?;
-        let mut accounts = self.bets.accounts(guild_id.get())?;
-        // sort by balance+inbet first and balance to tie break
-        accounts.sort_by_key(|acc| (acc.balance+acc.in_bet, acc.balance));
-        accounts.reverse();
-        let msg = format!("{}  ({} in bet)   user\n", config.currency, config.currency) 
-        + &accounts.into_iter().take(10).map(|acc| 
-            format!("{}  ({})   <@{}>", acc.balance, acc.in_bet, acc.user)
-        ).join("\n") + "\n...";
-        command.response(&ctx.http, MessageBuilder::new(msg), InteractionResponseFlags::default()).await?;
-        Ok(())
+    fn history_display(&self, server: u64, user: u64, page: usize) -> Result<(String, usize)> {
+        let cfg = self.bot_db.guild_config(server)?;
+        let pages = ((self.bot_db.transaction_count(server, user)? + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+        let page = page.min(pages - 1);
+        let transactions = self.bot_db.transactions(server, user, page * PAGE_SIZE, PAGE_SIZE)?;
+        if transactions.is_empty() {
+            return Ok(("*No transactions yet*".to_string(), pages));
+        }
+        let lines = transactions.iter().map(|tx| {
+            let bet = match tx.bet.map(|bet| self.bets.get_info(bet)) {
+                Some(Result::Ok(info)) => format!(" · {}", shorten(&info.desc, 30)),
+                _ => String::new()
+            };
+            format!("`{:+}` {} {}{}  <t:{}:R>", tx.amount, cfg.currency, tx.kind.label(), bet, tx.time)
+        }).collect::<Vec<String>>();
+        Ok((format!("**History** (page {}/{})\n{}", page + 1, pages, lines.join("\n")), pages))
     }
 
-    async fn is_admin(&self, command: &ComponentInteraction) -> Result<bool> {
-        if let Some(member) = &command.user.member {
-            let permissions = member.permissions.ok_or(anyhow!("couldn't get permissions"))?;
-            return Ok(permissions.administrator());
-        }
-        bail!("couldn't get member");
+    fn history_components(page: usize, pages: usize) -> Vec<CreateActionRow> {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::History(page.saturating_sub(1)).to_string())
+                .label("◀ Previous").style(ButtonStyle::Secondary).disabled(page == 0),
+            CreateButton::new(BetAction::History(page + 1).to_string())
+                .label("Next ▶").style(ButtonStyle::Secondary).disabled(page + 1 >= pages)
+        ])]
     }
 
-    pub async fn check_rights(&self, ctx: &Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        let user_uuid = command.user.id.get();
-        let info = self.bets.get_info(bet_id)?;
-        if info.author != user_uuid && !self.is_admin(command).await? {
-            command.response(
-                &ctx.http, 
-                MessageBuilder::new("Only the bet author or admins can perform this action").ephemeral(true),
-                InteractionResponseFlags::EPHEMERAL
-            ).await?;
-            bail!("user is not bet author and not admin");
-        }
+    pub async fn history_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let (msg, pages) = self.history_display(server_uuid, command.user.id.get(), 0)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(msg)
+                    .components(Self::history_components(0, pages))
+                    .ephemeral(true)
+            )
+        ).await?;
         Ok(())
     }
-    
-    pub async fn lock_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        self.check_rights(&ctx, command, bet_id).await?;
-        self.bets.lock_bet(bet_id)?;
+
+    pub async fn history_action(&self, ctx: Context, command: &ComponentInteraction, page: usize) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let (msg, pages) = self.history_display(server_uuid, command.user.id.get(), page)?;
         command.create_response(
-            &ctx.http,
+            &ctx.http, 
             CreateInteractionResponse::UpdateMessage(
-                CreateInteractionResponseMessage::new().components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::Abort().to_string()).label("🚫 Abort".to_string()).style(ButtonStyle::Secondary)
-                ])])
+                CreateInteractionResponseMessage::new()
+                    .content(msg)
+                    .components(Self::history_components(page.min(pages - 1), pages))
             )
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
-            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
-            let msg_id = self.msg_map.get(outcome.clone())?;
-            let mut message = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-            message.edit(&ctx.http, 
-                EditMessage::new().components(vec![CreateActionRow::Buttons(vec![
-                    CreateButton::new(BetAction::Resolve(outcome).to_string())
-                        .label("🏆 Resolve".to_string())
-                        .style(ButtonStyle::Secondary)
-                ])])
+        Ok(())
+    }
+
+    pub async fn stats_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let user_uuid = match option_value(&command, "user") {
+            Some(CommandDataOptionValue::User(user_id)) => user_id.get(),
+            _ => command.user.id.get()
+        };
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let stats = Stats::from(&self.bot_db.bet_results(server_uuid, user_uuid)?);
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(stats.display(user_uuid, &cfg))
+                    .allowed_mentions(CreateAllowedMentions::new())
+                    .ephemeral(true)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn bets_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        const MAX_CHARS: usize = 1950;
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let statuses = match option_str(&command, "status") {
+            Some(status) => vec![BetStatus::try_from(status.as_str())?],
+            None => vec![BetStatus::Open, BetStatus::Locked]
+        };
+        let creator = match option_value(&command, "creator") {
+            Some(CommandDataOptionValue::User(user_id)) => Some(user_id.get()),
+            _ => None
+        };
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let entries: Vec<BetEntry> = self.bot_db.bets(server_uuid, &statuses)?.into_iter().rev()
+            .filter(|entry| creator.map_or(true, |creator| entry.author == creator))
+            .collect();
+        let mut lines: Vec<String> = Vec::new();
+        let mut length = 0;
+        for entry in entries.iter() {
+            let desc = match self.bets.get_info(entry.bet) {
+                Result::Ok(info) => shorten(&info.desc, 50),
+                Err(_) => "*unknown bet*".to_string()
+            };
+            let wagers = self.bot_db.wagers(entry.bet)?;
+            let pool: u64 = wagers.iter().map(|(_, _, amount)| amount).sum();
+            let participants = wagers.iter().map(|(_, user, _)| user).unique().count();
+            let issue = self.bot_db.issue(entry.bet)?.map(|issue| format!(" · ⚠️ {}", issue)).unwrap_or_default();
+            let line = format!(
+                "[{}](https://discord.com/channels/{}/{}/{}) · {} · {} {} · {} 👥 · by <@{}>{}", 
+                desc, server_uuid, entry.channel, entry.bet, entry.status.label(), pool, cfg.currency, participants, entry.author, issue
+            );
+            length += line.chars().count() + 1;
+            if length > MAX_CHARS {
+                break;
+            }
+            lines.push(line);
+        }
+        if entries.len() > lines.len() {
+            lines.push(format!("*…and {} more*", entries.len() - lines.len()));
+        }
+        let msg = if lines.is_empty() { "*No bets found*".to_string() } else { lines.join("\n") };
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(msg)
+                    .allowed_mentions(CreateAllowedMentions::new())
+                    .ephemeral(true)
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn treasury_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let (user, amount) = match (option_value(&command, "user"), option_value(&command, "amount")) {
+            (Some(CommandDataOptionValue::User(user_id)), Some(CommandDataOptionValue::Integer(amount))) => (user_id.get(), *amount),
+            (None, None) => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(format!(
+                        "Treasury: {} {} | Rake: {}% of every resolved pool | Jackpot: {} {}", 
+                        self.bot_db.treasury(server_uuid)?, cfg.currency, cfg.rake, self.bot_db.jackpot(server_uuid)?, cfg.currency
+                    )).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                return Ok(());
+            },
+            _ => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new("To pay a prize, give both a user and an amount.").ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("prize without user or amount");
+            }
+        };
+        if !is_admin(command.member.as_deref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Paying prizes from the treasury requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
             ).await?;
+            bail!("user is not admin");
         }
+        self.balance_create(server_uuid, user)?;
+        let paid = self.bot_db.treasury_pay(server_uuid, -amount, || {
+            self.bets.change_balance(server_uuid, user, amount)?;
+            Ok(())
+        });
+        let treasury = match paid {
+            Result::Ok(treasury) => treasury,
+            Err(why) => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(format!("Couldn't pay {} {}: {}", amount, cfg.currency, why)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!(why);
+            }
+        };
+        self.bot_db.add_transaction(server_uuid, user, amount, TxKind::Prize, None)?;
+        command.response(
+            &ctx.http, 
+            MessageBuilder::new(format!(
+                "🎁 <@{}> received a prize of {} {} from the treasury\n*Treasury: {} {}*", 
+                user, amount, cfg.currency, treasury, cfg.currency
+            )),
+            InteractionResponseFlags::default()
+        ).await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
         Ok(())
     }
 
-    pub async fn abort_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
-        self.check_rights(&ctx, command, bet_id).await?;
-        self.bets.abort_bet(bet_id)?;
+    pub async fn leaderboard_command(
+        &self,
+        ctx: Context,
+        command: CommandInteraction,
+    ) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        let permanent = matches!(option_value(&command, "permanent"), Some(CommandDataOptionValue::Boolean(true)));
+        if permanent {
+            if !is_admin(command.member.as_deref()) {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new("Posting a permanent leaderboard requires administrator permissions.").ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("user is not admin");
+            }
+            let msg = format!(
+                "{}\n{}", PERMANENT_HEADER, 
+                leaderboard_display(&self.bets, &self.bot_db, server_uuid, LeaderboardSort::NetWorth, 0, None)?
+            );
+            let leaderboard_msg = command.response(&ctx.http, MessageBuilder::new(msg), InteractionResponseFlags::default()).await?;
+            if let Some((channel, message)) = self.bot_db.leaderboard(server_uuid)? {
+                if let Err(why) = ctx.http.delete_message(ChannelId::new(channel), message.into(), None).await {
+                    warn!(target: "betting-bot", "couldn't delete the previous leaderboard {}: {}", message, why);
+                }
+            }
+            self.bot_db.set_leaderboard(server_uuid, leaderboard_msg.channel_id.get(), leaderboard_msg.id.get())?;
+            return Ok(());
+        }
+        let sort = LeaderboardSort::NetWorth;
+        let pages = page_count(&self.bets, server_uuid)?;
         command.create_response(
             &ctx.http, 
-            CreateInteractionResponse::UpdateMessage(
-                CreateInteractionResponseMessage::new().content("*Bet aborted, participants have been refunded.*")
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(leaderboard_display(&self.bets, &self.bot_db, server_uuid, sort, 0, Some(command.user.id.get()))?)
+                    .components(leaderboard_components(sort, 0, pages))
+                    .allowed_mentions(CreateAllowedMentions::new())
             )
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
-            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
-            let msg_id = self.msg_map.get(outcome.clone())?;
-            ctx.http.delete_message(command.channel_id, msg_id.into(), None).await?;
-        }
         Ok(())
     }
 
-    pub async fn bet_click_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+    pub async fn leaderboard_action(
+        &self, ctx: Context, command: &ComponentInteraction, sort: LeaderboardSort, page: usize
+    ) -> Result<()> {
         let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
-        let user_uuid = command.user.id.get();
-        let balance = self.balance_create(server_uuid, user_uuid)?;
-        let bet_info = self.bets.get_info(bet_outcome.bet_id)?;
-        let previous_bet = match self.bets.position(user_uuid, bet_outcome.bet_id) {
-            Result::Ok(position) => {
-                if position.outcome != bet_outcome.outcome_id {
+        let pages = page_count(&self.bets, server_uuid)?;
+        let page = page.min(pages - 1);
+        let leaderboard = CreateInteractionResponseMessage::new()
+            .content(leaderboard_display(&self.bets, &self.bot_db, server_uuid, sort, page, Some(command.user.id.get()))?)
+            .components(leaderboard_components(sort, page, pages))
+            .allowed_mentions(CreateAllowedMentions::new());
+        let response = if command.message.flags.is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL)) {
+            CreateInteractionResponse::UpdateMessage(leaderboard)
+        } else {
+            CreateInteractionResponse::Message(leaderboard.ephemeral(true))
+        };
+        command.create_response(&ctx.http, response).await?;
+        Ok(())
+    }
+
+    pub async fn leaderboard_sort_action(&self, ctx: Context, command: &ComponentInteraction) -> Result<()> {
+        let sort = match &command.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => LeaderboardSort::try_from(
+                values.first().ok_or(anyhow!("no leaderboard sort selected"))?.as_str()
+            )?,
+            kind => bail!("expected a string select, got {:?}", kind)
+        };
+        self.leaderboard_action(ctx, command, sort, 0).await
+    }
+
+    pub async fn config_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        if !is_admin(command.member.as_deref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Configuring the bot requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not admin");
+        }
+        let keys = match option_str(&command, "key") {
+            Some(key) => vec![key],
+            None => GUILD_KEYS.iter().map(|key| key.to_string()).collect()
+        };
+        if let (Some(value), [key]) = (option_str(&command, "value"), keys.as_slice()) {
+            if value == "default" {
+                self.bot_db.unset_config(server_uuid, key)?;
+            } else {
+                if let Err(why) = self.bot_db.guild_config(server_uuid)?.set(key, &value) {
                     command.response(
                         &ctx.http, 
-                        MessageBuilder::new(
-                            format!("You put a bet on option #{} and can only bet on one option", position.outcome+1)
-                        ).ephemeral(true),
+                        MessageBuilder::new(format!("Invalid value '{}' for {}: {}", value, key, why)).ephemeral(true),
                         InteractionResponseFlags::EPHEMERAL
                     ).await?;
-                    bail!("user tried to bet on multiple option");        
+                    bail!(why);
                 }
-                position.amount
-            },
-            Err(betting::BetError::NotFound) => 0,
-            Err(err) => bail!(err)
-        };
-        command.create_response(
+                self.bot_db.set_config(server_uuid, key, &value)?;
+            }
+        }
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let overrides = self.bot_db.config_overrides(server_uuid)?;
+        let mut lines = Vec::new();
+        for key in keys {
+            let source = if overrides.iter().any(|(k, _)| *k == key) { "" } else { " *(default)*" };
+            lines.push(format!("{}: `{}`{}", key, cfg.get(&key)?, source));
+        }
+        command.response(
             &ctx.http, 
-            CreateInteractionResponse::Modal(
-                CreateModal::new(
-                    BetAction::BetOrder().to_string(), 
-                    format!("[{} {}] {}", balance, config.currency, shorten(&bet_info.desc, 20))
-                ).components(vec![
-                    CreateActionRow::InputText(
-                        CreateInputText::new(
-                            InputTextStyle::Short, 
-                            format!(
-                                "[{} {}] Bet on: {}", previous_bet, config.currency, 
-                                shorten(&command.message.content, 20)
-                            ),
-                            bet_outcome.to_string()
-                        ).placeholder("100").required(true)
-                    )
-                ])
-            )).await?;
+            MessageBuilder::new(lines.join("\n")).ephemeral(true),
+            InteractionResponseFlags::EPHEMERAL
+        ).await?;
         Ok(())
     }
 
-    pub async fn bet_order_action(&self, ctx: Context, command: &ModalInteraction) -> Result<()> {
-        let user = command.user.id.get();
-        if let ActionRowComponent::InputText(input) = &(&command.data.components[0]).components[0] {
-            let bet_outcome = BetOutcome::try_from(input.custom_id.as_ref())?;
-            let amount: u64 = <Option<String> as Clone>::clone(&input.value).unwrap().parse()?;
-            let (acc_update, bet) = self.bets.bet_on(bet_outcome.bet_id, bet_outcome.outcome_id, user, amount)?;
-            let total: u64 = bet.outcomes[bet_outcome.outcome_id].wagers
-                .iter().filter(|(u, _)| *u == user).map(|(_, a)| a).sum();
+    pub async fn channels_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        if !is_admin(command.member.as_deref()) {
             command.response(
                 &ctx.http, 
-                MessageBuilder::new(format!(
-                    "Succesfully bet {} {} (total {} {}) on:\n> {}\nnew balance: {} {}", 
-                    amount, config.currency, total, config.currency, bet.outcomes[bet_outcome.outcome_id].desc, acc_update.balance, config.currency
-                )).ephemeral(true),
+                MessageBuilder::new("Configuring the channels requires administrator permissions.").ephemeral(true),
                 InteractionResponseFlags::EPHEMERAL
             ).await?;
-            for (i, outcome) in outcomes_display(&bet).iter().enumerate() {
-                let msg_id = self.msg_map.get(BetOutcome { bet_id: bet_outcome.bet_id, outcome_id: i })?;
-                let mut msg = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-                msg.edit(&ctx.http, EditMessage::new().content(outcome)).await?;
-            }
+            bail!("user is not admin");
+        }
+        let channel = match option_value(&command, "channel") {
+            Some(CommandDataOptionValue::Channel(channel_id)) => Some(channel_id.get()),
+            _ => None
+        };
+        match (option_str(&command, "action").as_deref(), channel) {
+            (Some("add"), Some(channel)) => self.bot_db.add_betting_channel(server_uuid, channel)?,
+            (Some("remove"), Some(channel)) => self.bot_db.remove_betting_channel(server_uuid, channel)?,
+            (Some("results"), channel) => self.bot_db.set_results_channel(server_uuid, channel)?,
+            (Some("list") | None, _) => {},
+            (Some(action), None) => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(format!("A channel is needed to {} it", action)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("no channel given to {}", action);
+            },
+            (Some(action), _) => bail!("Channel action '{}' not recognized", action)
         }
+        let channels = self.bot_db.betting_channels(server_uuid)?;
+        let betting = if channels.is_empty() {
+            "anywhere".to_string()
+        } else {
+            channels.iter().map(|channel| format!("<#{}>", channel)).join(", ")
+        };
+        let results = match self.bot_db.results_channel(server_uuid)? {
+            Some(channel) => format!("<#{}>", channel),
+            None => "under each bet".to_string()
+        };
+        command.response(
+            &ctx.http, 
+            MessageBuilder::new(format!("Bets: {}\nResults: {}", betting, results)).ephemeral(true),
+            InteractionResponseFlags::EPHEMERAL
+        ).await?;
         Ok(())
     }
 
-    pub async fn resolve_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
-        self.check_rights(&ctx, command, bet_outcome.bet_id).await?;
-        self.bets.resolve(bet_outcome.bet_id, bet_outcome.outcome_id)?;
-
+    pub async fn abort_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        if !is_admin(command.member.as_deref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Aborting a bet by id requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not admin");
+        }
+        let bet_id = option_str(&command, "bet").ok_or(anyhow!("expected a bet id"))?;
+        let entry = match bet_id.trim().parse::<u64>().ok().and_then(|bet_id| self.bot_db.bet(bet_id).ok()) {
+            Some(entry) if entry.server == server_uuid && matches!(entry.status, BetStatus::Open | BetStatus::Locked) => entry,
+            _ => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(format!("No active bet with the id {} in this server", bet_id)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("no active bet {} in {}", bet_id, server_uuid);
+            }
+        };
+        self.refund_bet(server_uuid, entry.bet)?;
+        info!(target: "betting-bot", "{} aborted bet {} by id", command.user.id, entry.bet);
         command.response(
             &ctx.http, 
-            MessageBuilder::new(format!("🏆 Winner\n{}", command.message.content.clone())), 
-            InteractionResponseFlags::default()
-        ).await?;
-
-        let mut bet_msg = ctx.http.get_message(command.channel_id, bet_outcome.bet_id.into()).await?;
-        let bet_msg_content = bet_msg.content.clone();
-        bet_msg.edit(&ctx.http, 
-            EditMessage::new()
-                .content(format!("*Resolved {}*\n{}", Local::now().format("%d/%m/%Y"), bet_msg_content))
-                .components(vec![])
+            MessageBuilder::new("*Bet aborted, participants have been refunded.*").ephemeral(true),
+            InteractionResponseFlags::EPHEMERAL
         ).await?;
-        for outcome_id in self.bets.outcomes_of_bet(bet_outcome.bet_id)? {
-            let msg_id = self.msg_map.get(BetOutcome { bet_id: bet_outcome.bet_id, outcome_id: outcome_id as usize })?;
-            let mut message = ctx.http.get_message(command.channel_id, msg_id.into()).await?;
-            message.edit(&ctx.http, EditMessage::new().components(vec![])).await?;
+        let channel = ChannelId::new(entry.channel);
+        if let Err(why) = channel.edit_message(
+            &ctx.http, entry.bet, 
+            EditMessage::new().content("*Bet aborted, participants have been refunded.*").embeds(vec![]).components(vec![])
+        ).await {
+            warn!(target: "betting-bot", "couldn't edit bet message {}: {}", entry.bet, why);
         }
+        self.delete_outcome_messages(&ctx.http, channel, entry.bet).await?;
+        self.close_pending(&ctx.http, entry.bet, "*Bet aborted*").await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
         Ok(())
     }
 
-    pub async fn register_commands(&self, http: &Http, id: GuildId) {
-        println!("Registering slash commands for Guild {}", id);
-        if let Err(why) =
+    pub async fn adjust_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("command used outside a server"))?.get();
+        if !is_admin(command.member.as_deref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Adjusting balances requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not admin");
+        }
+        let (user, amount) = match (option_value(&command, "user"), option_value(&command, "amount")) {
+            (Some(CommandDataOptionValue::User(user_id)), Some(CommandDataOptionValue::Integer(amount))) => (user_id.get(), *amount),
+            _ => bail!("expected a user and an amount")
+        };
+        let action = option_str(&command, "action").ok_or(anyhow!("expected an action"))?;
+        let reason = option_str(&command, "reason").ok_or(anyhow!("expected a reason"))?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let balance = self.balance_create(server_uuid, user)? as i64;
+        let diff = match action.as_str() {
+            "credit" => amount,
+            "debit" => -amount,
+            "set" => amount - balance,
+            _ => bail!("Adjustment '{}' not recognized", action)
+        };
+        if balance + diff < 0 {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(format!(
+                    "Can't debit {} {} from a balance of {} {}", amount, cfg.currency, balance, cfg.currency
+                )).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("adjustment would make the balance negative");
+        }
+        let new_balance = self.balance_add(server_uuid, user, diff, TxKind::Adjustment)?;
+        self.bot_db.add_adjustment(server_uuid, user, command.user.id.get(), diff, &reason)?;
+        info!(
+            target: "betting-bot", "{} adjusted the balance of {} in {} by {}: {}", 
+            command.user.id, user, server_uuid, diff, reason
+        );
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "🛠️ <@{}> adjusted the balance of <@{}> by {:+} {} (new balance: {} {})\n> {}", 
+                        command.user.id, user, diff, cfg.currency, new_balance, cfg.currency, reason
+                    ))
+                    .allowed_mentions(CreateAllowedMentions::new())
+            )
+        ).await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
+        Ok(())
+    }
+
+    pub async fn reset_command(&self, ctx: Context, command: CommandInteraction) -> Result<()> {
+        if !is_admin(command.member.as_deref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Resetting requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not admin");
+        }
+        command.response(
+            &ctx.http,
+            MessageBuilder::new(
+                "⚠️ RESETTING WILL:\n1/ ABORT EVERY ACTIVE BET\n2/ RESET EVERY ACCOUNT TO THE STARTING SUM\n(administrator only)"
+            ).buttons(vec![
+                Button { custom_id: BetAction::ResetCancel().to_string(), label: "Cancel".to_string(), style: ButtonStyle::Secondary },
+                Button { custom_id: BetAction::Reset().to_string(), label: "RESET".to_string(), style: ButtonStyle::Danger }
+            ]),
+            InteractionResponseFlags::default()
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn reset_action(&self, ctx: Context, command: &ComponentInteraction, confirmed: bool) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        if !is_admin(command.member.as_ref()) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Resetting requires administrator permissions.").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not admin");
+        }
+        if !confirmed {
+            command.create_response(
+                &ctx.http, 
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().content("*Reset cancelled.*").components(vec![])
+                )
+            ).await?;
+            return Ok(());
+        }
+        let tracked = self.bot_db.bets(server_uuid, &[BetStatus::Open, BetStatus::Locked])?;
+        // bets from before the bot kept track of them are only known to the `betting` crate
+        for bet in self.bets.active_bets(server_uuid)? {
+            if !tracked.iter().any(|entry| entry.bet == bet) {
+                self.ledgered(server_uuid, TxKind::Refund, Some(bet), || Ok(((), self.abort_refunds(server_uuid, bet)?)))?;
+                self.forget_render(bet)?;
+            }
+        }
+        for bet in tracked {
+            self.ledgered(server_uuid, TxKind::Refund, Some(bet.bet), || Ok(((), self.abort_refunds(server_uuid, bet.bet)?)))?;
+            self.bot_db.set_status(bet.bet, BetStatus::Aborted)?;
+            self.forget_render(bet.bet)?;
+            let channel = ChannelId::new(bet.channel);
+            self.delete_outcome_messages(&ctx.http, channel, bet.bet).await?;
+            if let Err(why) = channel.edit_message(
+                &ctx.http, bet.bet, 
+                EditMessage::new().content("*Bet aborted by a reset, participants have been refunded.*").embeds(vec![]).components(vec![])
+            ).await {
+                warn!(target: "betting-bot", "couldn't edit bet message {}: {}", bet.bet, why);
+            }
+            if let Err(why) = self.close_pending(&ctx.http, bet.bet, "*Bet aborted by a reset*").await {
+                warn!(target: "betting-bot", "couldn't close the pending result of bet {}: {:?}", bet.bet, why);
+            }
+        }
+        let starting_coins = self.bot_db.guild_config(server_uuid)?.starting_coins;
+        for account in self.bets.accounts(server_uuid)? {
+            let diff = starting_coins as i64 - account.balance as i64;
+            if diff != 0 {
+                self.balance_add(server_uuid, account.user, diff, TxKind::Reset)?;
+            }
+        }
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content("*Every bet has been aborted and every account has been reset.*")
+                    .components(vec![])
+            )
+        ).await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
+        Ok(())
+    }
+
+    async fn delete_outcome_messages(&self, http: &Http, channel: ChannelId, bet_id: u64) -> Result<()> {
+        let outcome_ids = if self.bot_db.is_compact(bet_id)? { Vec::new() } else { self.bets.outcomes_of_bet(bet_id)? };
+        for outcome_id in outcome_ids {
+            let Result::Ok(msg_id) = self.msg_map.get(BetOutcome { bet_id, outcome_id: outcome_id as usize }) else {
+                continue;
+            };
+            match http.delete_message(channel, msg_id.into(), None).await {
+                Err(why) if is_not_found(&why) => {},
+                res => res?
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_manager(&self, command: &ComponentInteraction, cfg: &Config) -> bool {
+        command.member.as_ref().is_some_and(|member| has_role(member, cfg.manager_role))
+    }
+
+    pub async fn check_rights(&self, ctx: &Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        let info = self.bets.get_info(bet_id)?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        if info.author != user_uuid && !self.is_manager(command, &cfg) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Only the bet author or bet managers can perform this action").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user is not bet author and not bet manager");
+        }
+        Ok(())
+    }
+    
+    pub async fn lock_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        self.check_rights(&ctx, command, bet_id).await?;
+        self.bets.lock_bet(bet_id)?;
+        self.bot_db.set_status(bet_id, BetStatus::Locked)?;
+        self.bot_db.remove_auto_lock(bet_id)?;
+        command.create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(self.locked_header(bet_id)?).components(locked_bet_components(&self.outcome_descs(bet_id)?))
+            )
+        ).await?;
+        self.lock_outcomes(&ctx.http, command.channel_id, bet_id).await
+    }
+
+    pub fn locked_header(&self, bet_id: u64) -> Result<String> {
+        let info = self.bets.get_info(bet_id)?;
+        let mut header = bet_header(&info.desc, None, self.bot_db.policy(bet_id)?);
+        if let Some(fixed_odds) = self.bot_db.fixed_odds(bet_id)? {
+            let cfg = self.bot_db.guild_config(self.bot_db.bet(bet_id)?.server)?;
+            header += &fixed_odds_line(info.author, &fixed_odds, &cfg.currency);
+        }
+        Ok(header)
+    }
+
+    pub fn outcome_descs(&self, bet_id: u64) -> Result<Vec<String>> {
+        let descs = self.bot_db.outcomes(bet_id)?;
+        if !descs.is_empty() {
+            return Ok(descs);
+        }
+        Ok(self.bets.outcomes_of_bet(bet_id)?.into_iter().map(|outcome_id| format!("Option {}", outcome_id + 1)).collect())
+    }
+
+    pub async fn lock_outcomes(&self, http: &Http, channel_id: ChannelId, bet_id: u64) -> Result<()> {
+        if self.bot_db.is_compact(bet_id)? {
+            return Ok(());
+        }
+        for outcome_id in self.bets.outcomes_of_bet(bet_id)? {
+            let outcome = BetOutcome { bet_id, outcome_id: outcome_id as usize };
+            let msg_id = self.msg_map.get(outcome.clone())?;
+            let mut message = http.get_message(channel_id, msg_id.into()).await?;
+            message.edit(http, EditMessage::new().components(resolve_components(outcome))).await?;
+        }
+        Ok(())
+    }
+
+    fn refund_bet(&self, server_uuid: u64, bet_id: u64) -> Result<()> {
+        let fixed_odds = self.bot_db.fixed_odds(bet_id)?;
+        let author = self.bets.get_info(bet_id)?.author;
+        self.ledgered(server_uuid, TxKind::Refund, Some(bet_id), || {
+            let mut refunds = self.abort_refunds(server_uuid, bet_id)?;
+            if let Some(fixed_odds) = &fixed_odds {
+                self.bets.change_balance(server_uuid, author, fixed_odds.escrow as i64)?;
+                *refunds.entry(author).or_default() += fixed_odds.escrow as i64;
+            }
+            Ok(((), refunds))
+        })?;
+        self.bot_db.set_status(bet_id, BetStatus::Aborted)?;
+        self.bot_db.remove_auto_lock(bet_id)?;
+        self.bot_db.clear_issue(bet_id)?;
+        self.forget_render(bet_id)
+    }
+
+    pub async fn abort_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        self.check_rights(&ctx, command, bet_id).await?;
+        self.refund_bet(server_uuid, bet_id)?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content("*Bet aborted, participants have been refunded.*").embeds(vec![]).components(vec![])
+            )
+        ).await?;
+        self.delete_outcome_messages(&ctx.http, command.channel_id, bet_id).await?;
+        self.close_pending(&ctx.http, bet_id, "*Bet aborted*").await?;
+        self.leaderboard_update(&ctx.http, server_uuid).await;
+        Ok(())
+    }
+
+    async fn check_position(&self, ctx: &Context, command: &ComponentInteraction, bet_outcome: &BetOutcome) -> Result<u64> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        if self.bot_db.guild_config(server_uuid)?.author_bets == AuthorPolicy::NoBetting 
+            && self.bets.get_info(bet_outcome.bet_id)?.author == user_uuid {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("You can't bet on your own bets in this server").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("author tried to bet on their own bet");
+        }
+        let positions = self.bot_db.positions(bet_outcome.bet_id, user_uuid)?;
+        if self.bot_db.policy(bet_outcome.bet_id)? == BetPolicy::Single {
+            if let Some((outcome, _)) = positions.iter().find(|(outcome, _)| *outcome != bet_outcome.outcome_id) {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(
+                        format!("You put a bet on option #{} and can only bet on one option", outcome+1)
+                    ).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("user tried to bet on multiple option");        
+            }
+        }
+        Ok(positions.iter().filter(|(outcome, _)| *outcome == bet_outcome.outcome_id).map(|(_, amount)| amount).sum())
+    }
+
+    fn wager(&self, server: u64, user: u64, bet_outcome: &BetOutcome, amount: u64) -> Result<(String, Bet)> {
+        // checked before moving the stake, a failing bet would otherwise leave it withdrawn
+        if matches!(self.bot_db.bet(bet_outcome.bet_id), Result::Ok(entry) if entry.status != BetStatus::Open) {
+            bail!("user tried to bet on bet {} which isn't open anymore", bet_outcome.bet_id);
+        }
+        let balance = self.bets.balance(server, user)?;
+        if amount > balance {
+            bail!("user tried to bet {} with a balance of {}", amount, balance);
+        }
+        let mut withdrawn = Vec::new();
+        if self.bot_db.policy(bet_outcome.bet_id)? == BetPolicy::Switch {
+            for (outcome, stake) in self.bot_db.positions(bet_outcome.bet_id, user)? {
+                if outcome != bet_outcome.outcome_id {
+                    self.bets.withdraw(bet_outcome.bet_id, outcome, user, stake)?;
+                    self.bot_db.add_transaction(server, user, stake as i64, TxKind::Withdrawal, Some(bet_outcome.bet_id))?;
+                    self.bot_db.add_wager(bet_outcome.bet_id, outcome, user, -(stake as i64))?;
+                    withdrawn.push((outcome, stake));
+                }
+            }
+        }
+        let moved: u64 = withdrawn.iter().map(|(_, stake)| stake).sum();
+        let (acc_update, bet) = match self.bets.bet_on(bet_outcome.bet_id, bet_outcome.outcome_id, user, amount + moved) {
+            Result::Ok(res) => res,
+            Err(why) => {
+                // the checks above can still race with a lock, the stake goes back where it was
+                for (outcome, stake) in withdrawn {
+                    self.bets.bet_on(bet_outcome.bet_id, outcome, user, stake)?;
+                    self.bot_db.add_transaction(server, user, -(stake as i64), TxKind::Wager, Some(bet_outcome.bet_id))?;
+                    self.bot_db.add_wager(bet_outcome.bet_id, outcome, user, stake as i64)?;
+                }
+                bail!(why);
+            }
+        };
+        self.bot_db.add_transaction(server, user, -((amount + moved) as i64), TxKind::Wager, Some(bet.bet))?;
+        self.bot_db.add_wager(bet_outcome.bet_id, bet_outcome.outcome_id, user, (amount + moved) as i64)?;
+        let cfg = self.bot_db.guild_config(server)?;
+        let moved_display = if moved > 0 { 
+            format!(" (+{} {} moved from your other options)", moved, cfg.currency) 
+        } else { 
+            String::new() 
+        };
+        let mut msg = format!(
+            "Succesfully bet {} {}{} on:\n> {}\nyour positions: {}\nnew balance: {} {}", 
+            amount, cfg.currency, moved_display, bet.outcomes[bet_outcome.outcome_id].desc, 
+            positions_display(&bet, user, &cfg.currency), acc_update.balance, cfg.currency
+        );
+        if cfg.author_bets == AuthorPolicy::OtherResolver && bet.author == user {
+            msg += "\n*You bet on your own bet, a bet manager will have to resolve it*";
+        }
+        Ok((msg, bet))
+    }
+
+    fn withdraw(&self, user: u64, bet_outcome: &BetOutcome, amount: u64) -> Result<(String, Bet)> {
+        let (acc_update, bet) = self.bets.withdraw(bet_outcome.bet_id, bet_outcome.outcome_id, user, amount)?;
+        self.bot_db.add_transaction(bet.server, user, amount as i64, TxKind::Withdrawal, Some(bet.bet))?;
+        self.bot_db.add_wager(bet_outcome.bet_id, bet_outcome.outcome_id, user, -(amount as i64))?;
+        let cfg = self.bot_db.guild_config(bet.server)?;
+        let positions = positions_display(&bet, user, &cfg.currency);
+        let msg = format!(
+            "Succesfully withdrew {} {} from:\n> {}\nyour positions: {}\nnew balance: {} {}", 
+            amount, cfg.currency, bet.outcomes[bet_outcome.outcome_id].desc, 
+            if positions.is_empty() { "none" } else { &positions }, acc_update.balance, cfg.currency
+        );
+        Ok((msg, bet))
+    }
+
+    pub async fn bet_click_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        let balance = self.balance_create(server_uuid, user_uuid)?;
+        let bet_info = self.bets.get_info(bet_outcome.bet_id)?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let previous_bet = self.check_position(&ctx, command, &bet_outcome).await?;
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::Modal(
+                CreateModal::new(
+                    BetAction::BetOrder().to_string(), 
+                    format!("[{} {}] {}", balance, cfg.currency, shorten(&bet_info.desc, 20))
+                ).components(vec![
+                    CreateActionRow::InputText(
+                        CreateInputText::new(
+                            InputTextStyle::Short, 
+                            format!(
+                                "[{} {}] Bet on: {}", previous_bet, cfg.currency, 
+                                shorten(self.outcome_descs(bet_outcome.bet_id)?.get(bet_outcome.outcome_id).map_or("", |desc| desc), 20)
+                            ),
+                            bet_outcome.to_string()
+                        ).placeholder("100 (-100 to withdraw)").required(true)
+                    )
+                ])
+            )).await?;
+        Ok(())
+    }
+
+    fn withdraw_check(&self, user: u64, bet_outcome: &BetOutcome, amount: u64) -> Result<Option<String>> {
+        if matches!(self.bot_db.bet(bet_outcome.bet_id), Result::Ok(entry) if entry.status != BetStatus::Open) {
+            return Ok(Some("Wagers can only be withdrawn while the bet is open".to_string()));
+        }
+        let wagered: u64 = self.bot_db.positions(bet_outcome.bet_id, user)?.iter()
+            .filter(|(outcome, _)| *outcome == bet_outcome.outcome_id).map(|(_, amount)| amount).sum();
+        if amount > wagered {
+            return Ok(Some(format!("You can't withdraw {} with only {} on this option", amount, wagered)));
+        }
+        let Some(fixed_odds) = self.bot_db.fixed_odds(bet_outcome.bet_id)? else {
+            return Ok(None);
+        };
+        // less stake to cover the other options with, the escrow must still cover them
+        let mut wagers = self.bot_db.wagers(bet_outcome.bet_id)?;
+        let mut left = amount;
+        for wager in wagers.iter_mut().filter(|(outcome, u, _)| *outcome == bet_outcome.outcome_id && *u == user) {
+            let taken = wager.2.min(left);
+            wager.2 -= taken;
+            left -= taken;
+        }
+        Ok(if fixed_odds.covers(&wagers)? {
+            None
+        } else {
+            Some(format!(
+                "The bookmaker couldn't cover the other options without your stake, their liability is capped at {}", 
+                fixed_odds.escrow
+            ))
+        })
+    }
+
+    fn liability_check(&self, user: u64, bet_outcome: &BetOutcome, amount: u64) -> Result<Option<String>> {
+        let Some(fixed_odds) = self.bot_db.fixed_odds(bet_outcome.bet_id)? else {
+            return Ok(None);
+        };
+        let mut wagers = self.bot_db.wagers(bet_outcome.bet_id)?;
+        if self.bot_db.policy(bet_outcome.bet_id)? == BetPolicy::Switch {
+            for wager in wagers.iter_mut().filter(|(_, u, _)| *u == user) {
+                wager.0 = bet_outcome.outcome_id;
+            }
+        }
+        wagers.push((bet_outcome.outcome_id, user, amount));
+        Ok(if fixed_odds.covers(&wagers)? {
+            None
+        } else {
+            Some(format!(
+                "The bookmaker can't cover a win of {} on this option, their liability is capped at {}", 
+                amount, fixed_odds.escrow
+            ))
+        })
+    }
+
+    pub async fn bet_order_action(&self, ctx: Context, command: &ModalInteraction) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user = command.user.id.get();
+        if let ActionRowComponent::InputText(input) = &(&command.data.components[0]).components[0] {
+            let bet_outcome = BetOutcome::try_from(input.custom_id.as_ref())?;
+            let amount: i64 = <Option<String> as Clone>::clone(&input.value).unwrap().trim().parse()?;
+            let (msg, bet) = if amount < 0 {
+                if let Some(why) = self.withdraw_check(user, &bet_outcome, amount.unsigned_abs())? {
+                    command.response(
+                        &ctx.http, 
+                        MessageBuilder::new(why.clone()).ephemeral(true),
+                        InteractionResponseFlags::EPHEMERAL
+                    ).await?;
+                    bail!(why);
+                }
+                self.withdraw(user, &bet_outcome, amount.unsigned_abs())?
+            } else {
+                if let Some(why) = self.liability_check(user, &bet_outcome, amount as u64)? {
+                    command.response(
+                        &ctx.http, 
+                        MessageBuilder::new(why.clone()).ephemeral(true),
+                        InteractionResponseFlags::EPHEMERAL
+                    ).await?;
+                    bail!(why);
+                }
+                self.wager(server_uuid, user, &bet_outcome, amount as u64)?
+            };
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(msg).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            self.outcomes_update(ctx.http.clone(), command.channel_id, bet)?;
+        }
+        Ok(())
+    }
+
+    pub async fn quick_bet_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome, amount: Amount) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        let balance = self.balance_create(server_uuid, user_uuid)?;
+        self.check_position(&ctx, command, &bet_outcome).await?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let stake = amount.of(balance);
+        if stake == 0 || stake > balance {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(format!(
+                    "You can't bet {} with a balance of {} {}", amount.label(&cfg.currency), balance, cfg.currency
+                )).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user doesn't have enough to bet {}", amount.to_string());
+        }
+        if let Some(why) = self.liability_check(user_uuid, &bet_outcome, stake)? {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(why.clone()).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!(why);
+        }
+        let (msg, bet) = self.wager(server_uuid, user_uuid, &bet_outcome, stake)?;
+        command.response(
+            &ctx.http, 
+            MessageBuilder::new(msg).ephemeral(true),
+            InteractionResponseFlags::EPHEMERAL
+        ).await?;
+        self.outcomes_update(ctx.http.clone(), command.channel_id, bet)?;
+        Ok(())
+    }
+
+    fn split_pool(&self, server: u64, bet_id: u64, winners: &[usize], rake: u32, jackpot: u64) -> Result<(u64, HashMap<u64, i64>)> {
+        let (raked, nets) = pool_nets(&self.wagers(server, bet_id)?, winners, rake, jackpot);
+        Ok((raked, self.settle_nets(server, bet_id, nets)?))
+    }
+
+    fn settle_nets(&self, server: u64, bet_id: u64, nets: HashMap<u64, i64>) -> Result<HashMap<u64, i64>> {
+        let mut diffs = self.abort_refunds(server, bet_id)?;
+        for (user, net) in nets {
+            if net != 0 {
+                self.bets.change_balance(server, user, net)?;
+                *diffs.entry(user).or_default() += net;
+            }
+        }
+        Ok(diffs)
+    }
+
+    fn resolve_single(&self, server: u64, bet_id: u64, winner: usize) -> Result<HashMap<u64, i64>> {
+        let wagers = self.wagers(server, bet_id)?;
+        let pool: u64 = wagers.iter().map(|(_, _, amount)| amount).sum();
+        let user = wagers.iter().find(|(outcome, _, _)| *outcome == winner).map(|(_, user, _)| *user)
+            .ok_or(anyhow!("nobody backed the winner of bet {}", bet_id))?;
+        self.bets.resolve(bet_id, winner)?;
+        Ok(HashMap::from([(user, pool as i64)]))
+    }
+
+    fn settle_fixed_odds(&self, server: u64, bet_id: u64, fixed_odds: &FixedOdds, winners: &[usize]) -> Result<HashMap<u64, i64>> {
+        let wagers = self.wagers(server, bet_id)?;
+        let author = self.bets.get_info(bet_id)?.author;
+        let (payouts, bookmaker) = fixed_odds.settle(&wagers, winners)?;
+        let mut nets: HashMap<u64, i64> = HashMap::new();
+        for (_, user, amount) in &wagers {
+            *nets.entry(*user).or_default() -= *amount as i64;
+        }
+        for (user, payout) in payouts {
+            *nets.entry(user).or_default() += payout as i64;
+        }
+        *nets.entry(author).or_default() += bookmaker as i64;
+        self.settle_nets(server, bet_id, nets)
+    }
+
+    fn no_winner(&self, server: u64, bet_id: u64, pool: u64, cfg: &Config) -> Result<String> {
+        Ok(match cfg.no_winner {
+            NoWinnerPolicy::Refund => {
+                self.ledgered(server, TxKind::Refund, Some(bet_id), || Ok(((), self.abort_refunds(server, bet_id)?)))?;
+                format!("*Nobody bet on the winner, the {} {} wagered have been refunded*", pool, cfg.currency)
+            },
+            NoWinnerPolicy::Jackpot => {
+                self.split_pool(server, bet_id, &[], 0, 0)?;
+                let jackpot = self.bot_db.jackpot_add(server, pool)?;
+                format!(
+                    "*Nobody bet on the winner, the {} {} wagered go to the winners of the next bet (jackpot: {} {})*", 
+                    pool, cfg.currency, jackpot, cfg.currency
+                )
+            },
+            NoWinnerPolicy::Treasury => {
+                self.split_pool(server, bet_id, &[], 0, 0)?;
+                self.bot_db.treasury_add(server, pool as i64)?;
+                format!("*Nobody bet on the winner, the {} {} wagered went to the treasury*", pool, cfg.currency)
+            }
+        })
+    }
+
+    async fn resolve(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64, winners: Vec<usize>) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        self.check_rights(&ctx, command, bet_id).await?;
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let user_uuid = command.user.id.get();
+        if cfg.author_bets == AuthorPolicy::OtherResolver && self.bets.get_info(bet_id)?.author == user_uuid 
+            && !self.bot_db.positions(bet_id, user_uuid)?.is_empty() {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("You have money on this bet, another bet manager has to resolve it").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("author with a position tried to resolve their own bet");
+        }
+        let mut settled = None;
+        match self.bot_db.pending_resolution(bet_id)? {
+            Some(pending) if pending.disputed => {
+                if !self.is_manager(command, &cfg) {
+                    command.response(
+                        &ctx.http, 
+                        MessageBuilder::new("This result is disputed, only a bet manager can settle it").ephemeral(true),
+                        InteractionResponseFlags::EPHEMERAL
+                    ).await?;
+                    bail!("user tried to settle a disputed result without being a bet manager");
+                }
+                // another bet manager may be confirming it at the same time
+                if !self.bot_db.claim_pending_resolution(&pending)? {
+                    command.response(
+                        &ctx.http, 
+                        MessageBuilder::new("This result was already settled").ephemeral(true),
+                        InteractionResponseFlags::EPHEMERAL
+                    ).await?;
+                    bail!("disputed result of bet {} settled twice", bet_id);
+                }
+                settled = Some(pending);
+            },
+            Some(_) if !self.is_manager(command, &cfg) => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new("A result is already pending, only a bet manager can replace it").ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+                bail!("user tried to replace a pending result without being a bet manager");
+            },
+            _ if cfg.dispute_window > 0 => return self.propose_resolution(&ctx, command, bet_id, winners, &cfg).await,
+            _ => {}
+        }
+        let announcement = self.pay_out(server_uuid, bet_id, &winners, &cfg)?;
+        let entry = self.bot_db.bet(bet_id)?;
+        match self.results_elsewhere(&ctx.http, &entry, &announcement).await {
+            Some(results) => {
+                command.response(
+                    &ctx.http, 
+                    MessageBuilder::new(format!("The results were announced in <#{}>", results)).ephemeral(true),
+                    InteractionResponseFlags::EPHEMERAL
+                ).await?;
+            },
+            None => {
+                command.response(&ctx.http, MessageBuilder::new(announcement), InteractionResponseFlags::default()).await?;
+            }
+        }
+        let note = format!("*Settled by <@{}>*", user_uuid);
+        match settled {
+            Some(pending) => self.pending_note(&ctx.http, &pending, &note).await?,
+            None => self.close_pending(&ctx.http, bet_id, &note).await?
+        }
+        self.show_resolved(&ctx.http, bet_id, &winners).await
+    }
+
+    pub fn pay_out(&self, server_uuid: u64, bet_id: u64, winners: &[usize], cfg: &Config) -> Result<String> {
+        // claimed before any money moves so that a second click or a stale button can't pay twice
+        if !self.bot_db.claim_resolution(bet_id)? {
+            bail!("bet {} is not locked anymore, it was already resolved or aborted", bet_id);
+        }
+        let descs = self.outcome_descs(bet_id)?;
+        let fixed_odds = self.bot_db.fixed_odds(bet_id)?;
+        let wagers = self.wagers(server_uuid, bet_id)?;
+        let pool: u64 = wagers.iter().map(|(_, _, amount)| amount).sum();
+        let backers = wagers.iter().filter(|(outcome, _, _)| winners.contains(outcome)).map(|(_, user, _)| user).unique().count();
+        let backed = backers > 0;
+        let mut notes = Vec::new();
+        if fixed_odds.is_none() && pool > 0 && !backed {
+            notes.push(self.no_winner(server_uuid, bet_id, pool, cfg)?);
+        } else {
+            // taken before paying out so that two resolutions can't both share it
+            let jackpot = if fixed_odds.is_none() && backed { self.bot_db.jackpot_take(server_uuid)? } else { 0 };
+            let paid = self.ledgered(server_uuid, TxKind::Payout, Some(bet_id), || match (&fixed_odds, winners, cfg.rake, jackpot) {
+                (Some(fixed_odds), _, _, _) => Ok((0, self.settle_fixed_odds(server_uuid, bet_id, fixed_odds, winners)?)),
+                // a pool split by the `betting` crate could round differently from what the ledger records
+                (None, [winner], 0, 0) if backers == 1 => Ok((0, self.resolve_single(server_uuid, bet_id, *winner)?)),
+                _ => self.split_pool(server_uuid, bet_id, winners, cfg.rake, jackpot)
+            });
+            if paid.is_err() && jackpot > 0 {
+                self.bot_db.jackpot_add(server_uuid, jackpot)?;
+            }
+            let raked = paid?;
+            if raked > 0 {
+                self.bot_db.treasury_add(server_uuid, raked as i64)?;
+                notes.push(format!("*{} {} went to the treasury*", raked, cfg.currency));
+            }
+            if jackpot > 0 {
+                notes.push(format!("*The jackpot of {} {} was shared between the winners*", jackpot, cfg.currency));
+            }
+        }
+        self.forget_render(bet_id)?;
+        for winner in winners {
+            self.bot_db.add_winner(bet_id, *winner)?;
+        }
+
+        let winner_lines = winners.iter()
+            .map(|winner| format!("> {}", descs.get(*winner).cloned().unwrap_or_default()))
+            .chain(notes)
+            .collect::<Vec<String>>().join("\n");
+        Ok(format!("🏆 {}\n{}", if winners.len() > 1 { "Winners" } else { "Winner" }, winner_lines))
+    }
+
+    pub async fn results_elsewhere(&self, http: &Http, entry: &BetEntry, announcement: &str) -> Option<ChannelId> {
+        let post = async {
+            match self.bot_db.results_channel(entry.server)?.map(ChannelId::new) {
+                Some(results) if results.get() != entry.channel => {
+                    http.send(results, MessageBuilder::new(format!(
+                        "[{}](https://discord.com/channels/{}/{}/{})\n{}", 
+                        shorten(&self.bets.get_info(entry.bet)?.desc, 100), entry.server, entry.channel, entry.bet, announcement
+                    ))).await?;
+                    Ok(Some(results))
+                },
+                _ => Ok(None)
+            }
+        };
+        match post.await {
+            Result::Ok(results) => results,
+            Err(why) => {
+                warn!(target: "betting-bot", "couldn't announce the results of bet {}: {:?}", entry.bet, why);
+                None
+            }
+        }
+    }
+
+    pub async fn show_resolved(&self, http: &Http, bet_id: u64, winners: &[usize]) -> Result<()> {
+        let entry = self.bot_db.bet(bet_id)?;
+        let channel_id = ChannelId::new(entry.channel);
+        let cfg = self.bot_db.guild_config(entry.server)?;
+        let mut bet_msg = http.get_message(channel_id, bet_id.into()).await?;
+        let bet_msg_content = bet_msg.content.clone();
+        let mut edit = EditMessage::new()
+            .content(format!("*Resolved {}*\n{}", Local::now().format("%d/%m/%Y"), bet_msg_content))
+            .components(vec![]);
+        let compact = self.bot_db.is_compact(bet_id)?;
+        if compact {
+            if let Some(bet) = self.tracked_bet(&entry)? {
+                edit = edit.embed(bet_embed(&bet, &cfg, self.bot_db.fixed_odds(bet_id)?.as_ref(), winners));
+            }
+        }
+        bet_msg.edit(http, edit).await?;
+        let outcome_ids = if compact { Vec::new() } else { self.bets.outcomes_of_bet(bet_id)? };
+        for outcome_id in outcome_ids {
+            let msg_id = self.msg_map.get(BetOutcome { bet_id, outcome_id: outcome_id as usize })?;
+            let mut message = http.get_message(channel_id, msg_id.into()).await?;
+            let mut edit = EditMessage::new().components(vec![]);
+            if winners.contains(&(outcome_id as usize)) {
+                edit = edit.content(format!("🏆 **Winner**\n{}", message.content));
+            }
+            message.edit(http, edit).await?;
+        }
+        self.leaderboard_update(http, entry.server).await;
+        Ok(())
+    }
+
+    pub async fn resolve_action(&self, ctx: Context, command: &ComponentInteraction, bet_outcome: BetOutcome) -> Result<()> {
+        self.resolve(ctx, command, bet_outcome.bet_id, vec![bet_outcome.outcome_id]).await
+    }
+
+    pub async fn resolve_select_action(&self, ctx: Context, command: &ComponentInteraction) -> Result<()> {
+        let winners = match &command.data.kind {
+            ComponentInteractionDataKind::StringSelect { values } => values.iter()
+                .map(|value| value.parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()?,
+            kind => bail!("expected a string select, got {:?}", kind)
+        };
+        self.resolve(ctx, command, command.message.id.get(), winners).await
+    }
+
+    pub async fn register_commands(&self, http: &Http, id: GuildId) {
+        println!("Registering slash commands for Guild {}", id);
+        if let Err(why) =
             id.set_commands(http, vec![
                 CreateCommand::new("account").description("Check how much you have in your account."),
                 CreateCommand::new("bet")
@@ -293,18 +1602,235 @@ impl BettingBot {
                         CommandOptionType::String, 
                         "options", 
                         "The possible outcomes of the bet"
-                    ).required(true)),
+                    ).required(true))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "lock_in", 
+                        "Lock the bet automatically after a while, ex: 30m, 2h, 1h30m"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "lock_at", 
+                        "Lock the bet automatically at a given time in the bot's timezone, ex: 21:00, 2024-06-01 21:00"
+                    ).required(false))
+                    .add_option(BetPolicy::ALL.iter().fold(
+                        CreateCommandOption::new(
+                            CommandOptionType::String, "policy", "Whether users can bet on several options, server setting by default"
+                        ),
+                        |option, policy| option.add_string_choice(policy.label(), policy.to_string())
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "odds", 
+                        "Fixed odds for each option instead of a shared pool, ex: 1.5 3 4.2"
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "liability", 
+                        "With fixed odds, how much you put up from your balance to pay the winners"
+                    ).min_int_value(1).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Boolean, 
+                        "compact", 
+                        "Show the bet as a single message, server setting by default"
+                    ).required(false)),
+                CreateCommand::new("give")
+                    .description("Give coins to someone.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Who to give to").required(true)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "How much to give").min_int_value(1).required(true)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "note", 
+                        "What it's for").required(false)
+                    ),
+                CreateCommand::new("bets")
+                    .description("List the bets of the server, open and locked ones by default.")
+                    .add_option(BetStatus::ALL.iter().fold(
+                        CreateCommandOption::new(CommandOptionType::String, "status", "Only list the bets with this status"),
+                        |option, status| option.add_string_choice(status.to_string(), status.to_string())
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "creator", 
+                        "Only list the bets created by this user").required(false)
+                    ),
+                CreateCommand::new("history").description("See the transactions of your account."),
+                CreateCommand::new("stats")
+                    .description("See how well someone bets.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Whose stats to see, yours by default").required(false)
+                    ),
+                CreateCommand::new("treasury")
+                    .description("See the server treasury, admins can pay prizes from it.")
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Who to pay a prize to (admin only)").required(false)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "How much to pay from the treasury").min_int_value(1).required(false)
+                    ),
                 CreateCommand::new("leaderboard")    
                     .description("Displays the leadeboard.")
                     .add_option(CreateCommandOption::new(
                         CommandOptionType::Boolean, 
                         "permanent", 
                         "To make a ever updating leaderboard").required(false)
+                    ),
+                CreateCommand::new("config")
+                    .description("View or change the bot settings of this server (admin only).")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(GUILD_KEYS.iter().fold(
+                        CreateCommandOption::new(CommandOptionType::String, "key", "The setting to view or change"), 
+                        |option, key| option.add_string_choice(*key, *key)
+                    ).required(false))
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "value", 
+                        "The new value, 'default' to use the bot default").required(false)
+                    ),
+                CreateCommand::new("channels")
+                    .description("Choose where bets are made and where results are announced (admin only).")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "action", 
+                        "What to do with the channel, shows the current channels by default")
+                        .add_string_choice("add a betting channel", "add")
+                        .add_string_choice("remove a betting channel", "remove")
+                        .add_string_choice("set the results channel (none to announce under the bets)", "results")
+                        .add_string_choice("list", "list")
+                        .required(false)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Channel, 
+                        "channel", 
+                        "The channel").required(false)
+                    ),
+                CreateCommand::new("adjust")
+                    .description("Credit, debit or set the balance of someone (admin only).")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::User, 
+                        "user", 
+                        "Whose balance to adjust").required(true)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "action", 
+                        "What to do with the amount")
+                        .add_string_choice("credit", "credit")
+                        .add_string_choice("debit", "debit")
+                        .add_string_choice("set", "set")
+                        .required(true)
+                    )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::Integer, 
+                        "amount", 
+                        "How much").min_int_value(0).required(true)
                     )
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "reason", 
+                        "Why, kept in the logs").required(true)
+                    ),
+                CreateCommand::new("abort")
+                    .description("Abort an active bet from its id, when its messages are gone (admin only).")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
+                    .add_option(CreateCommandOption::new(
+                        CommandOptionType::String, 
+                        "bet", 
+                        "The id of the bet, the id of its message").required(true)
+                    ),
+                CreateCommand::new("reset")
+                    .description("Abort every bet and reset every account (admin only).")
+                    .default_member_permissions(Permissions::ADMINISTRATOR)
             ]
         ).await
         {
             println!("Couldn't register slash commmands: {}", why);
         };
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use super::{parse_duration, pool_nets};
+
+    #[test]
+    fn bare_number_is_minutes() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration(" 45 ").unwrap(), Duration::minutes(45));
+    }
+
+    #[test]
+    fn units_add_up() {
+        assert_eq!(parse_duration("45m").unwrap(), Duration::minutes(45));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("1d2h3m4s").unwrap(), Duration::seconds(93784));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("2w").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(parse_duration("99999999999999999d").is_err());
+        assert!(parse_duration(&i64::MAX.to_string()).is_err());
+    }
+
+    #[test]
+    fn pool_goes_to_the_winners() {
+        let (raked, nets) = pool_nets(&[(0, 1, 30), (1, 2, 10), (0, 3, 10), (2, 4, 50)], &[0], 0, 0);
+        assert_eq!(raked, 0);
+        assert_eq!(nets[&1], 45);
+        assert_eq!(nets[&2], -10);
+        assert_eq!(nets[&3], 15);
+        assert_eq!(nets[&4], -50);
+    }
+
+    #[test]
+    fn pool_split_between_several_winners() {
+        // a user on both winners gets both shares
+        let (_, nets) = pool_nets(&[(0, 1, 10), (1, 1, 10), (1, 2, 20), (2, 3, 60)], &[0, 1], 0, 0);
+        assert_eq!(nets[&1], 30);
+        assert_eq!(nets[&2], 30);
+        assert_eq!(nets[&3], -60);
+    }
+
+    #[test]
+    fn pool_minus_rake_plus_jackpot() {
+        let (raked, nets) = pool_nets(&[(0, 1, 50), (1, 2, 50)], &[0], 10, 25);
+        assert_eq!(raked, 10);
+        assert_eq!(nets[&1], 65);
+        assert_eq!(nets[&2], -50);
+        assert_eq!(nets.values().sum::<i64>(), 25 - 10);
+    }
+
+    #[test]
+    fn pool_without_winners_is_kept() {
+        let (raked, nets) = pool_nets(&[(0, 1, 50), (1, 2, 50)], &[], 0, 0);
+        assert_eq!(raked, 0);
+        assert_eq!(nets[&1], -50);
+        assert_eq!(nets[&2], -50);
+    }
+}
@@ -1,21 +1,18 @@
-use std::{
-    sync::{atomic::Ordering, Arc},
-    time::Duration,
-};
+use std::sync::atomic::Ordering;
 use anyhow::anyhow;
-use log::{warn, info};
+use log::warn;
 use serenity::{
     async_trait,
     model::{
         gateway::Ready,
         guild::Guild,
-        id::GuildId,
+        id::{ChannelId, GuildId, MessageId},
         application::{Interaction, InteractionResponseFlags}
     },
     prelude::*,
 };
 use serenity_utils::{is_writable, MessageBuilder, CommandUtil};
-use crate::{config::config, betting_bot::BettingBot, serialize_utils::BetAction};
+use crate::{betting_bot::BettingBot, serialize_utils::BetAction};
 
 #[async_trait]
 impl EventHandler for BettingBot {
@@ -28,7 +25,17 @@ impl EventHandler for BettingBot {
                     if let Err(why) = match command_name.as_str() {
                         "account" => self.account_command(ctx, command).await,
                         "bet" => self.bet_command(ctx, command).await,
+                        "bets" => self.bets_command(ctx, command).await,
                         "leaderboard" => self.leaderboard_command(ctx, command).await,
+                        "config" => self.config_command(ctx, command).await,
+                        "history" => self.history_command(ctx, command).await,
+                        "stats" => self.stats_command(ctx, command).await,
+                        "treasury" => self.treasury_command(ctx, command).await,
+                        "give" => self.give_command(ctx, command).await,
+                        "channels" => self.channels_command(ctx, command).await,
+                        "adjust" => self.adjust_command(ctx, command).await,
+                        "abort" => self.abort_command(ctx, command).await,
+                        "reset" => self.reset_command(ctx, command).await,
                         _ => Err(anyhow!("Unknown command")),
                     } {
                         warn!(target: "betting-bot", "\\{}: {:?}", command_name, why);
@@ -45,7 +52,18 @@ impl EventHandler for BettingBot {
                 Ok(BetAction::Lock()) => self.lock_action(ctx, &command, command.message.id.get()).await,
                 Ok(BetAction::Abort()) => self.abort_action(ctx, &command, command.message.id.get()).await,
                 Ok(BetAction::BetClick(bet_outcome)) => self.bet_click_action(ctx, &command, bet_outcome).await,
+                Ok(BetAction::QuickBet(bet_outcome, amount)) => self.quick_bet_action(ctx, &command, bet_outcome, amount).await,
                 Ok(BetAction::Resolve(bet_outcome)) => self.resolve_action(ctx, &command, bet_outcome).await,
+                Ok(BetAction::ResolveSelect()) => self.resolve_select_action(ctx, &command).await,
+                Ok(BetAction::Reset()) => self.reset_action(ctx, &command, true).await,
+                Ok(BetAction::ResetCancel()) => self.reset_action(ctx, &command, false).await,
+                Ok(BetAction::Leaderboard(sort, page)) => self.leaderboard_action(ctx, &command, sort, page).await,
+                Ok(BetAction::LeaderboardSort()) => self.leaderboard_sort_action(ctx, &command).await,
+                Ok(BetAction::History(page)) => self.history_action(ctx, &command, page).await,
+                Ok(BetAction::Give(pending_id)) => self.give_action(ctx, &command, pending_id, true).await,
+                Ok(BetAction::GiveCancel(pending_id)) => self.give_action(ctx, &command, pending_id, false).await,
+                Ok(BetAction::Dispute(bet_id)) => self.dispute_action(ctx, &command, bet_id).await,
+                Ok(BetAction::DisputeConfirm(bet_id)) => self.dispute_confirm_action(ctx, &command, bet_id).await,
                 Err(why) => Err(why),
                 other => Err(anyhow!("Unhandled BetAction variant {:?}", other))
             } {
@@ -66,25 +84,34 @@ impl EventHandler for BettingBot {
         println!("{} is connected!", ready.user.name);
     }
 
-    async fn cache_ready(&self, _ctx: Context, _guilds: Vec<GuildId>) {
+    async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
         println!("Cache built successfully!");
-        let bets = Arc::new(self.bets.clone());
+        // balances may have changed while we were away
+        match self.bot_db.leaderboard_servers() {
+            Ok(servers) => for server in servers {
+                self.leaderboard_update(&ctx.http, server).await;
+            },
+            Err(why) => warn!(target: "betting-bot", "couldn't get the permanent leaderboards: {}", why)
+        }
         if !self.is_loop_running.load(Ordering::Relaxed) {
-            let bets1 = Arc::clone(&bets);
-            tokio::spawn(async move {
-                loop {
-                    tokio::time::sleep(Duration::from_secs(3600 * config.interval)).await;
-                    if let Err(why) = bets1.global_income(config.income as u64) {
-                        warn!(target: "betting-bot", "couldn't distribute global income: {}", why)
-                    } else {
-                        info!(target: "betting-bot", "distributed global income of {}", config.income)
-                    }
-                }
-            });
+            self.resync_bets(&ctx).await;
+            if let Err(why) = self.schedule_pending_locks(ctx.http.clone()) {
+                warn!(target: "betting-bot", "couldn't reschedule the pending locks: {:?}", why);
+            }
+            if let Err(why) = self.schedule_pending_resolutions(ctx.http.clone()) {
+                warn!(target: "betting-bot", "couldn't reschedule the pending resolutions: {:?}", why);
+            }
+            self.schedule_income(ctx);
             self.is_loop_running.swap(true, Ordering::Relaxed);
         }
     }
 
+    async fn message_delete(&self, _ctx: Context, _channel_id: ChannelId, deleted_message_id: MessageId, _guild_id: Option<GuildId>) {
+        if let Err(why) = self.bot_db.remove_leaderboard(deleted_message_id.get()) {
+            warn!(target: "betting-bot", "couldn't forget deleted message {}: {}", deleted_message_id, why);
+        }
+    }
+
     async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: Option<bool>) {
         self.register_commands(&ctx.http, guild.id).await;
     }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use betting::utils::lrm;
+const MAX_ODD: f64 = 1000.;
+
+/// A bet where the creator sets the odds and backs the payouts with an escrowed sum
+#[derive(Debug, Clone)]
+pub struct FixedOdds {
+    pub odds: Vec<f64>,
+    pub escrow: u64
+}
+
+impl FixedOdds {
+    /// Parses odds such as "1.5 3 4.2", one per outcome
+    pub fn parse(value: &str, outcomes: usize, escrow: u64) -> Result<Self> {
+        let odds = value.split_whitespace()
+            .map(|odd| odd.parse::<f64>().map_err(|_| anyhow!("'{}' is not a number", odd)))
+            .collect::<Result<Vec<f64>>>()?;
+        if odds.len() != outcomes {
+            bail!("expected {} odds, one per option, got {}", outcomes, odds.len());
+        }
+        if odds.iter().any(|odd| !(*odd > 1. && *odd <= MAX_ODD)) {
+            bail!("odds must be greater than 1 and {} at most", MAX_ODD);
+        }
+        Ok(FixedOdds { odds, escrow })
+    }
+
+    /// What the winning wager gets back, stake included
+    fn payout(&self, outcome: usize, amount: u64) -> Result<u64> {
+        let payout = (amount as f64 * self.odds[outcome]).floor();
+        if !(payout < i64::MAX as f64) {
+            bail!("a payout of {} at {} is too large", amount, self.odds[outcome]);
+        }
+        Ok(payout as u64)
+    }
+
+    /// What the bookmaker loses if the outcome wins, negative if they make money
+    pub fn loss(&self, wagers: &[(usize, u64, u64)], outcome: usize) -> Result<i64> {
+        let stakes = checked_sum(wagers.iter().map(|(_, _, amount)| *amount))?;
+        let payouts = checked_sum(wagers.iter()
+            .filter(|(o, _, _)| *o == outcome)
+            .map(|(o, _, amount)| self.payout(*o, *amount))
+            .collect::<Result<Vec<u64>>>()?)?;
+        Ok(i64::try_from(payouts)? - i64::try_from(stakes)?)
+    }
+
+    /// Whether the escrow covers the payouts whichever outcome wins
+    pub fn covers(&self, wagers: &[(usize, u64, u64)]) -> Result<bool> {
+        for outcome in 0..self.odds.len() {
+            if self.loss(wagers, outcome)? > i64::try_from(self.escrow)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The payout of every winning user (stake included) and what goes back to the bookmaker, 
+    /// if several winners cost more than the escrow and stakes, the payouts are scaled down to fit
+    pub fn settle(&self, wagers: &[(usize, u64, u64)], winners: &[usize]) -> Result<(HashMap<u64, u64>, u64)> {
+        let available = checked_sum(wagers.iter().map(|(_, _, amount)| *amount).chain([self.escrow]))?;
+        let winning: Vec<&(usize, u64, u64)> = wagers.iter().filter(|(o, _, _)| winners.contains(o)).collect();
+        let mut payouts = winning.iter().map(|(o, _, amount)| self.payout(*o, *amount)).collect::<Result<Vec<u64>>>()?;
+        if checked_sum(payouts.iter().copied())? > available {
+            payouts = lrm(available, &payouts);
+        }
+        let mut users: HashMap<u64, u64> = HashMap::new();
+        for ((_, user, _), payout) in winning.iter().zip(&payouts) {
+            *users.entry(*user).or_default() += payout;
+        }
+        Ok((users, available - payouts.iter().sum::<u64>()))
+    }
+}
+
+fn checked_sum(amounts: impl IntoIterator<Item = u64>) -> Result<u64> {
+    amounts.into_iter().try_fold(0u64, |sum, amount| sum.checked_add(amount)).ok_or(anyhow!("the amounts are too large"))
+}
+
+impl ToString for FixedOdds {
+    fn to_string(&self) -> String {
+        self.odds.iter().map(|odd| odd.to_string()).collect::<Vec<String>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedOdds;
+
+    #[test]
+    fn parse_checks_odds() {
+        assert_eq!(FixedOdds::parse("1.5 3", 2, 100).unwrap().odds, vec![1.5, 3.]);
+        assert!(FixedOdds::parse("1.5", 2, 100).is_err());
+        assert!(FixedOdds::parse("1.5 abc", 2, 100).is_err());
+        assert!(FixedOdds::parse("1 2", 2, 100).is_err());
+        assert!(FixedOdds::parse("NaN 2", 2, 100).is_err());
+        assert!(FixedOdds::parse("inf 2", 2, 100).is_err());
+        assert!(FixedOdds::parse("1e300 2", 2, 100).is_err());
+    }
+
+    #[test]
+    fn huge_payouts_are_errors() {
+        let fixed_odds = FixedOdds { odds: vec![1000., 2.], escrow: u64::MAX };
+        assert!(fixed_odds.loss(&[(0, 1, u64::MAX / 100)], 0).is_err());
+        assert!(fixed_odds.covers(&[(0, 1, u64::MAX / 100)]).is_err());
+        assert!(fixed_odds.settle(&[(1, 1, 10)], &[1]).is_err());
+    }
+
+    #[test]
+    fn covers_the_worst_outcome() {
+        let fixed_odds = FixedOdds { odds: vec![2., 3.], escrow: 10 };
+        assert!(fixed_odds.covers(&[(0, 1, 10)]).unwrap());
+        assert!(!fixed_odds.covers(&[(0, 1, 11)]).unwrap());
+        // the stakes on the other outcome pay for part of the loss
+        assert!(fixed_odds.covers(&[(0, 1, 20), (1, 2, 10)]).unwrap());
+    }
+
+    #[test]
+    fn settle_pays_at_the_odds() {
+        let fixed_odds = FixedOdds { odds: vec![2., 3.], escrow: 100 };
+        let (payouts, bookmaker) = fixed_odds.settle(&[(0, 1, 10), (1, 2, 10), (0, 1, 5)], &[0]).unwrap();
+        assert_eq!(payouts.len(), 1);
+        assert_eq!(payouts[&1], 30);
+        assert_eq!(bookmaker, 95);
+    }
+
+    #[test]
+    fn settle_scales_down_to_what_is_available() {
+        let fixed_odds = FixedOdds { odds: vec![2., 3.], escrow: 0 };
+        let (payouts, bookmaker) = fixed_odds.settle(&[(0, 1, 10), (1, 2, 10)], &[0, 1]).unwrap();
+        assert_eq!(payouts[&1], 8);
+        assert_eq!(payouts[&2], 12);
+        assert_eq!(bookmaker, 0);
+    }
+}
@@ -0,0 +1,1028 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use itertools::Itertools;
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::{bookmaker::FixedOdds, config::{config, Config}, policy::BetPolicy};
+pub const OPEN: &str = "open";
+pub const LOCKED: &str = "locked";
+pub const RESOLVED: &str = "resolved";
+pub const ABORTED: &str = "aborted";
+pub const ACCOUNT: &str = "account";
+pub const WAGER: &str = "wager";
+pub const PAYOUT: &str = "payout";
+pub const REFUND: &str = "refund";
+pub const WITHDRAWAL: &str = "withdrawal";
+pub const INCOME: &str = "income";
+pub const PRIZE: &str = "prize";
+pub const ESCROW: &str = "escrow";
+pub const TRANSFER: &str = "transfer";
+pub const ADJUSTMENT: &str = "adjustment";
+pub const RESET: &str = "reset";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetStatus {
+    Open,
+    Locked,
+    Resolved,
+    Aborted
+}
+
+impl BetStatus {
+    pub const ALL: [BetStatus; 4] = [BetStatus::Open, BetStatus::Locked, BetStatus::Resolved, BetStatus::Aborted];
+
+    pub fn label(&self) -> &str {
+        match self {
+            BetStatus::Open => "🟢 open",
+            BetStatus::Locked => "🔒 locked",
+            BetStatus::Resolved => "🏆 resolved",
+            BetStatus::Aborted => "🚫 aborted"
+        }
+    }
+}
+
+impl ToString for BetStatus {
+    fn to_string(&self) -> String {
+        match self {
+            BetStatus::Open => OPEN,
+            BetStatus::Locked => LOCKED,
+            BetStatus::Resolved => RESOLVED,
+            BetStatus::Aborted => ABORTED
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for BetStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            OPEN => BetStatus::Open,
+            LOCKED => BetStatus::Locked,
+            RESOLVED => BetStatus::Resolved,
+            ABORTED => BetStatus::Aborted,
+            _ => bail!("Bet status '{}' not recognized", value)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TxKind {
+    Account,
+    Wager,
+    Payout,
+    Refund,
+    Withdrawal,
+    Income,
+    Prize,
+    Escrow,
+    Transfer,
+    Adjustment,
+    Reset
+}
+
+impl TxKind {
+    pub fn label(&self) -> &str {
+        match self {
+            TxKind::Account => "account opened",
+            TxKind::Wager => "wager",
+            TxKind::Payout => "payout",
+            TxKind::Refund => "refund",
+            TxKind::Withdrawal => "withdrawal",
+            TxKind::Income => "passive income",
+            TxKind::Prize => "treasury prize",
+            TxKind::Escrow => "bookmaker escrow",
+            TxKind::Transfer => "transfer",
+            TxKind::Adjustment => "admin adjustment",
+            TxKind::Reset => "reset"
+        }
+    }
+}
+
+impl ToString for TxKind {
+    fn to_string(&self) -> String {
+        match self {
+            TxKind::Account => ACCOUNT,
+            TxKind::Wager => WAGER,
+            TxKind::Payout => PAYOUT,
+            TxKind::Refund => REFUND,
+            TxKind::Withdrawal => WITHDRAWAL,
+            TxKind::Income => INCOME,
+            TxKind::Prize => PRIZE,
+            TxKind::Escrow => ESCROW,
+            TxKind::Transfer => TRANSFER,
+            TxKind::Adjustment => ADJUSTMENT,
+            TxKind::Reset => RESET
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for TxKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            ACCOUNT => TxKind::Account,
+            WAGER => TxKind::Wager,
+            PAYOUT => TxKind::Payout,
+            REFUND => TxKind::Refund,
+            WITHDRAWAL => TxKind::Withdrawal,
+            INCOME => TxKind::Income,
+            PRIZE => TxKind::Prize,
+            ESCROW => TxKind::Escrow,
+            TRANSFER => TxKind::Transfer,
+            ADJUSTMENT => TxKind::Adjustment,
+            RESET => TxKind::Reset,
+            _ => bail!("Transaction kind '{}' not recognized", value)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub amount: i64,
+    pub kind: TxKind,
+    pub bet: Option<u64>,
+    pub time: i64
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub sender: u64,
+    pub receiver: u64,
+    pub amount: u64,
+    pub note: Option<String>,
+    pub time: i64
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingResolution {
+    pub bet: u64,
+    pub winners: Vec<usize>,
+    pub final_at: i64,
+    pub channel: u64,
+    pub message: u64,
+    pub disputed: bool
+}
+
+fn winners_parse(value: String) -> Vec<usize> {
+    value.split(',').filter_map(|winner| winner.parse().ok()).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct BetResult {
+    pub author: u64,
+    pub wagered: u64,
+    pub payout: u64,
+    pub won: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct BetEntry {
+    pub bet: u64,
+    pub server: u64,
+    pub channel: u64,
+    pub author: u64,
+    pub status: BetStatus
+}
+
+#[derive(Clone)]
+pub struct BotDB {
+    conn: Arc<Mutex<Connection>>
+}
+
+impl BotDB {
+    pub fn new(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS bet (
+                bet INTEGER PRIMARY KEY,
+                server INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                author INTEGER NOT NULL,
+                status TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS outcome (
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                desc TEXT NOT NULL,
+                PRIMARY KEY (bet, outcome)
+            );
+            CREATE TABLE IF NOT EXISTS fixed_odds (
+                bet INTEGER PRIMARY KEY,
+                odds TEXT NOT NULL,
+                escrow INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bet_policy (
+                bet INTEGER PRIMARY KEY,
+                policy TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS compact_bet (
+                bet INTEGER PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS bet_issue (
+                bet INTEGER PRIMARY KEY,
+                issue TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS auto_lock (
+                bet INTEGER PRIMARY KEY,
+                lock_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS wager (
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                PRIMARY KEY (bet, outcome, user)
+            );
+            CREATE TABLE IF NOT EXISTS winner (
+                bet INTEGER NOT NULL,
+                outcome INTEGER NOT NULL,
+                PRIMARY KEY (bet, outcome)
+            );
+            CREATE TABLE IF NOT EXISTS leaderboard (
+                server INTEGER PRIMARY KEY,
+                channel INTEGER NOT NULL,
+                message INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS income (
+                server INTEGER PRIMARY KEY,
+                last_paid INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS treasury (
+                server INTEGER PRIMARY KEY,
+                balance INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS jackpot (
+                server INTEGER PRIMARY KEY,
+                amount INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS ledger (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                server INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                bet INTEGER,
+                time INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS ledger_user ON ledger (server, user);
+            CREATE TABLE IF NOT EXISTS transfer (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                server INTEGER NOT NULL,
+                sender INTEGER NOT NULL,
+                receiver INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                note TEXT,
+                time INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS pending_transfer (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                server INTEGER NOT NULL,
+                sender INTEGER NOT NULL,
+                receiver INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                note TEXT
+            );
+            CREATE TABLE IF NOT EXISTS adjustment (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                server INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                admin INTEGER NOT NULL,
+                amount INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                time INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS pending_resolution (
+                bet INTEGER PRIMARY KEY,
+                winners TEXT NOT NULL,
+                final_at INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                message INTEGER NOT NULL,
+                disputed INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS dispute (
+                bet INTEGER NOT NULL,
+                user INTEGER NOT NULL,
+                PRIMARY KEY (bet, user)
+            );
+            CREATE TABLE IF NOT EXISTS betting_channel (
+                server INTEGER NOT NULL,
+                channel INTEGER NOT NULL,
+                PRIMARY KEY (server, channel)
+            );
+            CREATE TABLE IF NOT EXISTS results_channel (
+                server INTEGER PRIMARY KEY,
+                channel INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS guild_config (
+                server INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (server, key)
+            );"
+        )?;
+        Ok(BotDB { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    fn conn(&self) -> Result<MutexGuard<Connection>> {
+        self.conn.lock().map_err(|_| anyhow!("bot db mutex is poisoned"))
+    }
+
+    pub fn add_bet(&self, bet: u64, server: u64, channel: u64, author: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO bet (bet, server, channel, author, status) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![bet, server, channel, author, BetStatus::Open.to_string()]
+        )?;
+        Ok(())
+    }
+
+    pub fn add_outcomes(&self, bet: u64, outcomes: &[String]) -> Result<()> {
+        let conn = self.conn()?;
+        for (i, desc) in outcomes.iter().enumerate() {
+            conn.execute(
+                "INSERT OR REPLACE INTO outcome (bet, outcome, desc) VALUES (?1, ?2, ?3)",
+                params![bet, i, desc]
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn outcomes(&self, bet: u64) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT desc FROM outcome WHERE bet = ?1 ORDER BY outcome")?;
+        let rows = stmt.query_map(params![bet], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_status(&self, bet: u64, status: BetStatus) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE bet SET status = ?2 WHERE bet = ?1",
+            params![bet, status.to_string()]
+        )?;
+        Ok(())
+    }
+
+    pub fn claim_resolution(&self, bet: u64) -> Result<bool> {
+        Ok(self.conn()?.execute(
+            "UPDATE bet SET status = ?2 WHERE bet = ?1 AND status = ?3",
+            params![bet, BetStatus::Resolved.to_string(), BetStatus::Locked.to_string()]
+        )? > 0)
+    }
+
+    pub fn bet(&self, bet: u64) -> Result<BetEntry> {
+        let (bet, server, channel, author, status) = self.conn()?.query_row(
+            "SELECT bet, server, channel, author, status FROM bet WHERE bet = ?1",
+            params![bet], |row| Ok((
+                row.get::<_, u64>(0)?, row.get::<_, u64>(1)?, row.get::<_, u64>(2)?,
+                row.get::<_, u64>(3)?, row.get::<_, String>(4)?
+            ))
+        )?;
+        Ok(BetEntry { bet, server, channel, author, status: BetStatus::try_from(status.as_str())? })
+    }
+
+    pub fn bets(&self, server: u64, statuses: &[BetStatus]) -> Result<Vec<BetEntry>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT bet, server, channel, author, status FROM bet WHERE server = ?1 ORDER BY bet"
+        )?;
+        let rows = stmt.query_map(params![server], |row| Ok((
+            row.get::<_, u64>(0)?, row.get::<_, u64>(1)?, row.get::<_, u64>(2)?,
+            row.get::<_, u64>(3)?, row.get::<_, String>(4)?
+        )))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (bet, server, channel, author, status) = row?;
+            let status = BetStatus::try_from(status.as_str())?;
+            if statuses.contains(&status) {
+                entries.push(BetEntry { bet, server, channel, author, status });
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn set_compact(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("INSERT OR IGNORE INTO compact_bet (bet) VALUES (?1)", params![bet])?;
+        Ok(())
+    }
+
+    pub fn is_compact(&self, bet: u64) -> Result<bool> {
+        Ok(self.conn()?.query_row(
+            "SELECT 1 FROM compact_bet WHERE bet = ?1", params![bet], |_| Ok(())
+        ).optional()?.is_some())
+    }
+
+    pub fn set_issue(&self, bet: u64, issue: &str) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO bet_issue (bet, issue) VALUES (?1, ?2)",
+            params![bet, issue]
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_issue(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM bet_issue WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn issue(&self, bet: u64) -> Result<Option<String>> {
+        Ok(self.conn()?.query_row(
+            "SELECT issue FROM bet_issue WHERE bet = ?1", params![bet], |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn set_policy(&self, bet: u64, policy: BetPolicy) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO bet_policy (bet, policy) VALUES (?1, ?2)",
+            params![bet, policy.to_string()]
+        )?;
+        Ok(())
+    }
+
+    pub fn policy(&self, bet: u64) -> Result<BetPolicy> {
+        let policy: Option<String> = self.conn()?.query_row(
+            "SELECT policy FROM bet_policy WHERE bet = ?1", params![bet], |row| row.get(0)
+        ).optional()?;
+        match policy {
+            Some(policy) => BetPolicy::try_from(policy.as_str()),
+            None => Ok(BetPolicy::Single)
+        }
+    }
+
+    pub fn set_fixed_odds(&self, bet: u64, fixed_odds: &FixedOdds) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO fixed_odds (bet, odds, escrow) VALUES (?1, ?2, ?3)",
+            params![bet, fixed_odds.to_string(), fixed_odds.escrow]
+        )?;
+        Ok(())
+    }
+
+    pub fn fixed_odds(&self, bet: u64) -> Result<Option<FixedOdds>> {
+        let row: Option<(String, u64)> = self.conn()?.query_row(
+            "SELECT odds, escrow FROM fixed_odds WHERE bet = ?1", params![bet], |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+        match row {
+            Some((odds, escrow)) => Ok(Some(FixedOdds::parse(&odds, odds.split_whitespace().count(), escrow)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn set_auto_lock(&self, bet: u64, lock_at: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO auto_lock (bet, lock_at) VALUES (?1, ?2)",
+            params![bet, lock_at]
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_auto_lock(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM auto_lock WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn auto_locks(&self) -> Result<Vec<(u64, i64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT bet, lock_at FROM auto_lock")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn add_wager(&self, bet: u64, outcome: usize, user: u64, amount: i64) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO wager (bet, outcome, user, amount) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (bet, outcome, user) DO UPDATE SET amount = amount + excluded.amount",
+            params![bet, outcome, user, amount]
+        )?;
+        conn.execute(
+            "DELETE FROM wager WHERE bet = ?1 AND outcome = ?2 AND user = ?3 AND amount <= 0",
+            params![bet, outcome, user]
+        )?;
+        Ok(())
+    }
+
+    pub fn positions(&self, bet: u64, user: u64) -> Result<Vec<(usize, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT outcome, amount FROM wager WHERE bet = ?1 AND user = ?2 ORDER BY outcome"
+        )?;
+        let rows = stmt.query_map(params![bet, user], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn wagers(&self, bet: u64) -> Result<Vec<(usize, u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT outcome, user, amount FROM wager WHERE bet = ?1")?;
+        let rows = stmt.query_map(params![bet], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn add_winner(&self, bet: u64, outcome: usize) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR IGNORE INTO winner (bet, outcome) VALUES (?1, ?2)",
+            params![bet, outcome]
+        )?;
+        Ok(())
+    }
+
+    pub fn win_counts(&self, server: u64) -> Result<Vec<(u64, u64, u64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT wager.user, COUNT(DISTINCT wager.bet), COUNT(DISTINCT winner.bet)
+            FROM wager JOIN bet ON bet.bet = wager.bet
+            LEFT JOIN winner ON winner.bet = wager.bet AND winner.outcome = wager.outcome
+            WHERE bet.server = ?1 AND bet.status = ?2 
+                AND NOT EXISTS(SELECT 1 FROM ledger WHERE ledger.bet = bet.bet AND ledger.kind = ?3)
+            GROUP BY wager.user"
+        )?;
+        let rows = stmt.query_map(
+            params![server, BetStatus::Resolved.to_string(), TxKind::Refund.to_string()], 
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn betting_profits(&self, server: u64) -> Result<Vec<(u64, i64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT user, SUM(amount) FROM ledger WHERE server = ?1 AND kind IN (?2, ?3, ?4, ?5, ?6) GROUP BY user"
+        )?;
+        let rows = stmt.query_map(
+            params![
+                server, TxKind::Wager.to_string(), TxKind::Payout.to_string(), TxKind::Refund.to_string(), 
+                TxKind::Withdrawal.to_string(), TxKind::Escrow.to_string()
+            ], 
+            |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn bet_results(&self, server: u64, user: u64) -> Result<Vec<BetResult>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT bet.author, SUM(wager.amount),
+                COALESCE((
+                    SELECT SUM(amount) FROM ledger 
+                    WHERE ledger.bet = bet.bet AND ledger.user = ?2 AND ledger.kind = ?4
+                ), 0),
+                EXISTS(
+                    SELECT 1 FROM winner JOIN wager AS own ON own.bet = winner.bet AND own.outcome = winner.outcome
+                    WHERE winner.bet = bet.bet AND own.user = ?2
+                )
+            FROM wager JOIN bet ON bet.bet = wager.bet
+            WHERE bet.server = ?1 AND wager.user = ?2 AND bet.status = ?3 
+                AND NOT EXISTS(SELECT 1 FROM ledger WHERE ledger.bet = bet.bet AND ledger.kind = ?5)
+            GROUP BY bet.bet ORDER BY bet.bet"
+        )?;
+        let rows = stmt.query_map(
+            params![server, user, BetStatus::Resolved.to_string(), TxKind::Payout.to_string(), TxKind::Refund.to_string()],
+            |row| Ok(BetResult { 
+                author: row.get(0)?, wagered: row.get(1)?, payout: row.get(2)?, won: row.get(3)? 
+            })
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_leaderboard(&self, server: u64, channel: u64, message: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO leaderboard (server, channel, message) VALUES (?1, ?2, ?3)",
+            params![server, channel, message]
+        )?;
+        Ok(())
+    }
+
+    pub fn leaderboard(&self, server: u64) -> Result<Option<(u64, u64)>> {
+        Ok(self.conn()?.query_row(
+            "SELECT channel, message FROM leaderboard WHERE server = ?1",
+            params![server], |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?)
+    }
+
+    pub fn leaderboard_servers(&self) -> Result<Vec<u64>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT server FROM leaderboard")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn remove_leaderboard(&self, message: u64) -> Result<()> {
+        self.conn()?.execute("DELETE FROM leaderboard WHERE message = ?1", params![message])?;
+        Ok(())
+    }
+
+    pub fn treasury(&self, server: u64) -> Result<u64> {
+        Ok(self.conn()?.query_row(
+            "SELECT balance FROM treasury WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?.unwrap_or(0))
+    }
+
+    pub fn treasury_add(&self, server: u64, diff: i64) -> Result<u64> {
+        self.treasury_pay(server, diff, || Ok(()))
+    }
+
+    /// Same as `treasury_add` but the change is only committed if `pay` succeeds, 
+    /// `pay` must not use the bot db as the connection is held meanwhile
+    pub fn treasury_pay(&self, server: u64, diff: i64, pay: impl FnOnce() -> Result<()>) -> Result<u64> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let treasury: i64 = tx.query_row(
+            "SELECT balance FROM treasury WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?.unwrap_or(0);
+        let balance = treasury + diff;
+        if balance < 0 {
+            bail!("the treasury only has {}", treasury);
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO treasury (server, balance) VALUES (?1, ?2)",
+            params![server, balance]
+        )?;
+        pay()?;
+        tx.commit()?;
+        Ok(balance as u64)
+    }
+
+    pub fn jackpot(&self, server: u64) -> Result<u64> {
+        Ok(self.conn()?.query_row(
+            "SELECT amount FROM jackpot WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?.unwrap_or(0))
+    }
+
+    pub fn jackpot_add(&self, server: u64, diff: u64) -> Result<u64> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let jackpot: i64 = tx.query_row(
+            "SELECT amount FROM jackpot WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?.unwrap_or(0);
+        let amount = jackpot + diff as i64;
+        tx.execute(
+            "INSERT OR REPLACE INTO jackpot (server, amount) VALUES (?1, ?2)",
+            params![server, amount]
+        )?;
+        tx.commit()?;
+        Ok(amount as u64)
+    }
+
+    pub fn jackpot_take(&self, server: u64) -> Result<u64> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let jackpot: u64 = tx.query_row(
+            "SELECT amount FROM jackpot WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?.unwrap_or(0);
+        tx.execute("UPDATE jackpot SET amount = 0 WHERE server = ?1", params![server])?;
+        tx.commit()?;
+        Ok(jackpot)
+    }
+
+    pub fn last_income(&self, server: u64) -> Result<Option<i64>> {
+        Ok(self.conn()?.query_row(
+            "SELECT last_paid FROM income WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn set_last_income(&self, server: u64, last_paid: i64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO income (server, last_paid) VALUES (?1, ?2)",
+            params![server, last_paid]
+        )?;
+        Ok(())
+    }
+
+    pub fn add_transaction(&self, server: u64, user: u64, amount: i64, kind: TxKind, bet: Option<u64>) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO ledger (server, user, amount, kind, bet, time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![server, user, amount, kind.to_string(), bet, Utc::now().timestamp()]
+        )?;
+        Ok(())
+    }
+
+    pub fn transactions(&self, server: u64, user: u64, offset: usize, limit: usize) -> Result<Vec<Transaction>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT amount, kind, bet, time FROM ledger WHERE server = ?1 AND user = ?2 
+            ORDER BY id DESC LIMIT ?3 OFFSET ?4"
+        )?;
+        let rows = stmt.query_map(params![server, user, limit, offset], |row| Ok((
+            row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<u64>>(2)?, row.get::<_, i64>(3)?
+        )))?;
+        let mut transactions = Vec::new();
+        for row in rows {
+            let (amount, kind, bet, time) = row?;
+            transactions.push(Transaction { amount, kind: TxKind::try_from(kind.as_str())?, bet, time });
+        }
+        Ok(transactions)
+    }
+
+    pub fn transaction_count(&self, server: u64, user: u64) -> Result<usize> {
+        Ok(self.conn()?.query_row(
+            "SELECT COUNT(*) FROM ledger WHERE server = ?1 AND user = ?2", params![server, user], |row| row.get(0)
+        )?)
+    }
+
+    pub fn add_transfer(&self, server: u64, sender: u64, receiver: u64, amount: u64, note: Option<&str>) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO transfer (server, sender, receiver, amount, note, time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![server, sender, receiver, amount, note, Utc::now().timestamp()]
+        )?;
+        Ok(())
+    }
+
+    pub fn transfers(&self, server: u64, user: u64, limit: usize) -> Result<Vec<Transfer>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT sender, receiver, amount, note, time FROM transfer 
+            WHERE server = ?1 AND (sender = ?2 OR receiver = ?2) ORDER BY id DESC LIMIT ?3"
+        )?;
+        let rows = stmt.query_map(params![server, user, limit], |row| Ok(Transfer { 
+            sender: row.get(0)?, receiver: row.get(1)?, amount: row.get(2)?, note: row.get(3)?, time: row.get(4)? 
+        }))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn sent_since(&self, server: u64, sender: u64, since: i64) -> Result<u64> {
+        Ok(self.conn()?.query_row(
+            "SELECT COALESCE(SUM(amount), 0) FROM transfer WHERE server = ?1 AND sender = ?2 AND time >= ?3",
+            params![server, sender, since], |row| row.get(0)
+        )?)
+    }
+
+    /// Replaces the pending transfer of the sender and returns its id, never reused so an older confirmation can't send it
+    pub fn set_pending_transfer(&self, server: u64, sender: u64, receiver: u64, amount: u64, note: Option<&str>) -> Result<u64> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM pending_transfer WHERE server = ?1 AND sender = ?2", params![server, sender])?;
+        conn.execute(
+            "INSERT INTO pending_transfer (server, sender, receiver, amount, note) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![server, sender, receiver, amount, note]
+        )?;
+        Ok(conn.last_insert_rowid() as u64)
+    }
+
+    pub fn take_pending_transfer(&self, server: u64, sender: u64, id: u64) -> Result<Option<(u64, u64, Option<String>)>> {
+        Ok(self.conn()?.query_row(
+            "DELETE FROM pending_transfer WHERE id = ?1 AND server = ?2 AND sender = ?3 RETURNING receiver, amount, note",
+            params![id, server, sender], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).optional()?)
+    }
+
+    pub fn set_pending_resolution(&self, bet: u64, winners: &[usize], final_at: i64, channel: u64, message: u64) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM dispute WHERE bet = ?1", params![bet])?;
+        conn.execute(
+            "INSERT OR REPLACE INTO pending_resolution (bet, winners, final_at, channel, message, disputed) 
+            VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+            params![bet, winners.iter().join(","), final_at, channel, message]
+        )?;
+        Ok(())
+    }
+
+    pub fn pending_resolution(&self, bet: u64) -> Result<Option<PendingResolution>> {
+        Ok(self.conn()?.query_row(
+            "SELECT bet, winners, final_at, channel, message, disputed FROM pending_resolution WHERE bet = ?1",
+            params![bet], |row| Ok(PendingResolution {
+                bet: row.get(0)?, winners: winners_parse(row.get(1)?), final_at: row.get(2)?,
+                channel: row.get(3)?, message: row.get(4)?, disputed: row.get(5)?
+            })
+        ).optional()?)
+    }
+
+    pub fn pending_resolutions(&self) -> Result<Vec<PendingResolution>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT bet, winners, final_at, channel, message, disputed FROM pending_resolution")?;
+        let rows = stmt.query_map([], |row| Ok(PendingResolution {
+            bet: row.get(0)?, winners: winners_parse(row.get(1)?), final_at: row.get(2)?,
+            channel: row.get(3)?, message: row.get(4)?, disputed: row.get(5)?
+        }))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn claim_pending_resolution(&self, pending: &PendingResolution) -> Result<bool> {
+        let conn = self.conn()?;
+        let claimed = conn.execute(
+            "DELETE FROM pending_resolution WHERE bet = ?1 AND final_at = ?2 AND message = ?3 AND disputed = ?4",
+            params![pending.bet, pending.final_at, pending.message, pending.disputed]
+        )? > 0;
+        if claimed {
+            conn.execute("DELETE FROM dispute WHERE bet = ?1", params![pending.bet])?;
+        }
+        Ok(claimed)
+    }
+
+    pub fn remove_pending_resolution(&self, bet: u64) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM pending_resolution WHERE bet = ?1", params![bet])?;
+        conn.execute("DELETE FROM dispute WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn add_dispute(&self, bet: u64, user: u64) -> Result<usize> {
+        let conn = self.conn()?;
+        conn.execute("INSERT OR IGNORE INTO dispute (bet, user) VALUES (?1, ?2)", params![bet, user])?;
+        Ok(conn.query_row("SELECT COUNT(*) FROM dispute WHERE bet = ?1", params![bet], |row| row.get(0))?)
+    }
+
+    pub fn set_disputed(&self, bet: u64) -> Result<()> {
+        self.conn()?.execute("UPDATE pending_resolution SET disputed = 1 WHERE bet = ?1", params![bet])?;
+        Ok(())
+    }
+
+    pub fn add_betting_channel(&self, server: u64, channel: u64) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR IGNORE INTO betting_channel (server, channel) VALUES (?1, ?2)",
+            params![server, channel]
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_betting_channel(&self, server: u64, channel: u64) -> Result<()> {
+        self.conn()?.execute(
+            "DELETE FROM betting_channel WHERE server = ?1 AND channel = ?2", params![server, channel]
+        )?;
+        Ok(())
+    }
+
+    pub fn betting_channels(&self, server: u64) -> Result<Vec<u64>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT channel FROM betting_channel WHERE server = ?1")?;
+        let rows = stmt.query_map(params![server], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_results_channel(&self, server: u64, channel: Option<u64>) -> Result<()> {
+        match channel {
+            Some(channel) => self.conn()?.execute(
+                "INSERT OR REPLACE INTO results_channel (server, channel) VALUES (?1, ?2)", params![server, channel]
+            )?,
+            None => self.conn()?.execute("DELETE FROM results_channel WHERE server = ?1", params![server])?
+        };
+        Ok(())
+    }
+
+    pub fn results_channel(&self, server: u64) -> Result<Option<u64>> {
+        Ok(self.conn()?.query_row(
+            "SELECT channel FROM results_channel WHERE server = ?1", params![server], |row| row.get(0)
+        ).optional()?)
+    }
+
+    pub fn add_adjustment(&self, server: u64, user: u64, admin: u64, amount: i64, reason: &str) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO adjustment (server, user, admin, amount, reason, time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![server, user, admin, amount, reason, Utc::now().timestamp()]
+        )?;
+        Ok(())
+    }
+
+    pub fn config_overrides(&self, server: u64) -> Result<Vec<(String, String)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT key, value FROM guild_config WHERE server = ?1")?;
+        let rows = stmt.query_map(params![server], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn set_config(&self, server: u64, key: &str, value: &str) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO guild_config (server, key, value) VALUES (?1, ?2, ?3)",
+            params![server, key, value]
+        )?;
+        Ok(())
+    }
+
+    pub fn unset_config(&self, server: u64, key: &str) -> Result<()> {
+        self.conn()?.execute(
+            "DELETE FROM guild_config WHERE server = ?1 AND key = ?2",
+            params![server, key]
+        )?;
+        Ok(())
+    }
+
+    pub fn guild_config(&self, server: u64) -> Result<Config> {
+        let mut guild_config = config.clone();
+        for (key, value) in self.config_overrides(server)? {
+            guild_config.set(&key, &value)?;
+        }
+        Ok(guild_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BotDB, BetStatus, TxKind};
+
+    fn db() -> BotDB {
+        BotDB::new(":memory:").unwrap()
+    }
+
+    fn resolved_bet(db: &BotDB, bet: u64, author: u64, wagers: &[(usize, u64, i64)], winner: usize) {
+        db.add_bet(bet, 1, 2, author).unwrap();
+        for (outcome, user, amount) in wagers {
+            db.add_wager(bet, *outcome, *user, *amount).unwrap();
+        }
+        db.add_winner(bet, winner).unwrap();
+        db.set_status(bet, BetStatus::Resolved).unwrap();
+    }
+
+    // bet 1 is won by user 5, bet 2 by user 6, bet 3 is refunded and bet 4 still locked
+    fn history() -> BotDB {
+        let db = db();
+        resolved_bet(&db, 1, 10, &[(0, 5, 50), (1, 5, 10), (1, 6, 40)], 0);
+        db.add_transaction(1, 5, 100, TxKind::Payout, Some(1)).unwrap();
+        resolved_bet(&db, 2, 20, &[(1, 5, 30), (0, 6, 30)], 0);
+        db.add_transaction(1, 6, 60, TxKind::Payout, Some(2)).unwrap();
+        resolved_bet(&db, 3, 10, &[(0, 5, 20), (1, 6, 20)], 1);
+        db.add_transaction(1, 5, 20, TxKind::Refund, Some(3)).unwrap();
+        db.add_transaction(1, 6, 20, TxKind::Refund, Some(3)).unwrap();
+        db.add_bet(4, 1, 2, 10).unwrap();
+        db.add_wager(4, 0, 5, 15).unwrap();
+        db.set_status(4, BetStatus::Locked).unwrap();
+        db
+    }
+
+    #[test]
+    fn bet_results_of_resolved_bets() {
+        let db = history();
+        let results = db.bet_results(1, 5).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].author, results[0].wagered, results[0].payout, results[0].won), (10, 60, 100, true));
+        assert_eq!((results[1].author, results[1].wagered, results[1].payout, results[1].won), (20, 30, 0, false));
+        assert!(db.bet_results(3, 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn win_counts_leave_refunds_out() {
+        let mut counts = history().win_counts(1).unwrap();
+        counts.sort();
+        assert_eq!(counts, vec![(5, 2, 1), (6, 2, 1)]);
+    }
+
+    #[test]
+    fn only_one_claim_of_a_pending_resolution() {
+        let db = db();
+        db.set_pending_resolution(1, &[0], 100, 2, 3).unwrap();
+        db.add_dispute(1, 5).unwrap();
+        let stale = db.pending_resolution(1).unwrap().unwrap();
+        db.set_disputed(1).unwrap();
+        assert!(!db.claim_pending_resolution(&stale).unwrap());
+        let pending = db.pending_resolution(1).unwrap().unwrap();
+        assert!(db.claim_pending_resolution(&pending).unwrap());
+        assert!(!db.claim_pending_resolution(&pending).unwrap());
+        assert!(db.pending_resolution(1).unwrap().is_none());
+        assert_eq!(db.add_dispute(1, 6).unwrap(), 1);
+    }
+
+    #[test]
+    fn new_pending_resolution_drops_the_disputes() {
+        let db = db();
+        db.set_pending_resolution(1, &[0], 100, 2, 3).unwrap();
+        db.add_dispute(1, 5).unwrap();
+        let previous = db.pending_resolution(1).unwrap().unwrap();
+        db.set_pending_resolution(1, &[1, 2], 200, 2, 4).unwrap();
+        assert!(!db.claim_pending_resolution(&previous).unwrap());
+        let pending = db.pending_resolution(1).unwrap().unwrap();
+        assert_eq!(pending.winners, vec![1, 2]);
+        assert_eq!(db.add_dispute(1, 5).unwrap(), 1);
+    }
+
+    #[test]
+    fn pending_transfer_taken_once() {
+        let db = db();
+        let first = db.set_pending_transfer(1, 5, 6, 100, None).unwrap();
+        let second = db.set_pending_transfer(1, 5, 7, 50, Some("thanks")).unwrap();
+        assert_ne!(first, second);
+        assert!(db.take_pending_transfer(1, 5, first).unwrap().is_none());
+        assert!(db.take_pending_transfer(1, 6, second).unwrap().is_none());
+        assert_eq!(db.take_pending_transfer(1, 5, second).unwrap(), Some((7, 50, Some("thanks".to_string()))));
+        assert!(db.take_pending_transfer(1, 5, second).unwrap().is_none());
+    }
+
+    #[test]
+    fn wagers_add_up_and_go_away_once_withdrawn() {
+        let db = db();
+        db.add_wager(1, 0, 5, 50).unwrap();
+        db.add_wager(1, 0, 5, 30).unwrap();
+        db.add_wager(1, 1, 5, 20).unwrap();
+        assert_eq!(db.positions(1, 5).unwrap(), vec![(0, 80), (1, 20)]);
+        db.add_wager(1, 0, 5, -30).unwrap();
+        assert_eq!(db.positions(1, 5).unwrap(), vec![(0, 50), (1, 20)]);
+        db.add_wager(1, 0, 5, -50).unwrap();
+        assert_eq!(db.wagers(1).unwrap(), vec![(1, 5, 20)]);
+    }
+
+    #[test]
+    fn only_one_claim_of_a_locked_bet() {
+        let db = db();
+        db.add_bet(1, 1, 2, 10).unwrap();
+        assert!(!db.claim_resolution(1).unwrap());
+        db.set_status(1, BetStatus::Locked).unwrap();
+        assert!(db.claim_resolution(1).unwrap());
+        assert!(!db.claim_resolution(1).unwrap());
+        assert_eq!(db.bet(1).unwrap().status, BetStatus::Resolved);
+    }
+
+    #[test]
+    fn jackpot_taken_once() {
+        let db = db();
+        db.jackpot_add(1, 30).unwrap();
+        assert_eq!(db.jackpot_add(1, 20).unwrap(), 50);
+        assert_eq!(db.jackpot_take(1).unwrap(), 50);
+        assert_eq!(db.jackpot_take(1).unwrap(), 0);
+        assert_eq!(db.jackpot(2).unwrap(), 0);
+    }
+}
@@ -1,42 +1,199 @@
 use serde::{Serialize, Deserialize};
 use lazy_static::lazy_static;
+use anyhow::{Context, Result, bail};
 use confy;
+use crate::{amount::Amount, policy::{AuthorPolicy, BetPolicy, NoWinnerPolicy}};
+
+// a row of buttons holds 5 at most, one of them is the custom bet button
+const MAX_BET_AMOUNTS: usize = 4;
+// a year, anything longer is surely a typo
+const MAX_INTERVAL: u64 = 8760;
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 struct PartialConfig {
     pub currency: String,
     pub starting_coins: u32,
     pub income: u32,
-    pub interval: u64
+    pub interval: u64,
+    pub bet_amounts: Vec<String>,
+    pub bet_policy: String,
+    pub rake: u32,
+    pub no_winner: String,
+    pub give_cap: u32,
+    pub give_confirm: u32,
+    pub compact: bool,
+    pub author_bets: String,
+    pub dispute_window: u32,
+    pub dispute_threshold: u32
 }
 
 impl Default for PartialConfig {
     fn default() -> Self {
         Self { 
             currency: "💵".to_string(), starting_coins: 350, 
-            income: 50, interval: 24
+            income: 50, interval: 24,
+            bet_amounts: vec!["10%".to_string(), "50%".to_string(), "100%".to_string()],
+            bet_policy: "single".to_string(),
+            rake: 0,
+            no_winner: "refund".to_string(),
+            give_cap: 0, give_confirm: 500,
+            compact: false,
+            author_bets: "free".to_string(),
+            dispute_window: 0, dispute_threshold: 3
         }
     }
 }
 
+/// The settings that can be overriden per server with /config
+pub const GUILD_KEYS: [&str; 16] = [
+    "currency", "starting_coins", "income", "interval", "bet_amounts", "bet_policy", "rake", "no_winner", "give_cap", "give_confirm", 
+    "compact", "manager_role", "creator_role", "author_bets", "dispute_window", "dispute_threshold"
+];
+
+/// Reads a role id, either raw or as a mention (<@&id>), "none" for no role
+fn parse_role(value: &str) -> Result<Option<u64>> {
+    let value = value.trim();
+    if value == "none" {
+        return Ok(None);
+    }
+    Ok(Some(value.trim_start_matches("<@&").trim_end_matches('>').parse()?))
+}
+
+/// Percentages first then fixed sums, each ascending, without duplicates (they would share a button id)
+fn parse_bet_amounts<'a>(values: impl IntoIterator<Item = &'a str>) -> Result<Vec<Amount>> {
+    let mut amounts = values.into_iter()
+        .map(|amount| Amount::try_from(amount).with_context(|| format!("invalid bet amount '{}'", amount)))
+        .collect::<Result<Vec<_>>>()?;
+    amounts.sort();
+    amounts.dedup();
+    amounts.truncate(MAX_BET_AMOUNTS);
+    Ok(amounts)
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub currency: String,
     pub starting_coins: u32,
     pub income: u32,
-    pub interval: u64
+    pub interval: u64,
+    pub bet_amounts: Vec<Amount>,
+    pub bet_policy: BetPolicy,
+    /// percentage of every resolved pool that goes to the treasury
+    pub rake: u32,
+    pub no_winner: NoWinnerPolicy,
+    /// how much a user can give in 24h, 0 for no limit
+    pub give_cap: u32,
+    /// gifts of this much or more ask for confirmation, 0 to never ask
+    pub give_confirm: u32,
+    /// new bets are a single message with an embed instead of a message per outcome
+    pub compact: bool,
+    /// can lock, abort and resolve any bet, roles only exist per server so there's no global default
+    pub manager_role: Option<u64>,
+    /// required to create bets if set
+    pub creator_role: Option<u64>,
+    /// whether authors can bet on their own bets
+    pub author_bets: AuthorPolicy,
+    /// minutes during which a result can be disputed before the payout, 0 to pay out right away
+    pub dispute_window: u32,
+    /// disputes needed to hold the payout until a bet manager settles it
+    pub dispute_threshold: u32
 }
 
 impl Config {
-    fn from(part_cfg: PartialConfig) -> Self {
-        Self {
+    fn from(part_cfg: PartialConfig) -> Result<Self> {
+        Ok(Self {
             currency: part_cfg.currency,
             starting_coins: part_cfg.starting_coins,
             income: part_cfg.income,
-            interval: part_cfg.interval
+            // the income boundaries are multiples of the interval
+            interval: part_cfg.interval.clamp(1, MAX_INTERVAL),
+            bet_amounts: parse_bet_amounts(part_cfg.bet_amounts.iter().map(String::as_str)).context("invalid bet_amounts")?,
+            bet_policy: BetPolicy::try_from(part_cfg.bet_policy.as_str()).context("invalid bet_policy")?,
+            rake: part_cfg.rake.min(100),
+            no_winner: NoWinnerPolicy::try_from(part_cfg.no_winner.as_str()).context("invalid no_winner")?,
+            give_cap: part_cfg.give_cap,
+            give_confirm: part_cfg.give_confirm,
+            compact: part_cfg.compact,
+            manager_role: None,
+            creator_role: None,
+            author_bets: AuthorPolicy::try_from(part_cfg.author_bets.as_str()).context("invalid author_bets")?,
+            dispute_window: part_cfg.dispute_window,
+            dispute_threshold: part_cfg.dispute_threshold.max(1)
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Result<String> {
+        Ok(match key {
+            "currency" => self.currency.clone(),
+            "starting_coins" => self.starting_coins.to_string(),
+            "income" => self.income.to_string(),
+            "interval" => self.interval.to_string(),
+            "bet_amounts" => self.bet_amounts.iter().map(Amount::to_string).collect::<Vec<_>>().join(" "),
+            "bet_policy" => self.bet_policy.to_string(),
+            "rake" => format!("{}%", self.rake),
+            "no_winner" => self.no_winner.to_string(),
+            "give_cap" => self.give_cap.to_string(),
+            "give_confirm" => self.give_confirm.to_string(),
+            "compact" => self.compact.to_string(),
+            "manager_role" => self.manager_role.map_or("none".to_string(), |role| role.to_string()),
+            "creator_role" => self.creator_role.map_or("none".to_string(), |role| role.to_string()),
+            "author_bets" => self.author_bets.to_string(),
+            "dispute_window" => format!("{} min", self.dispute_window),
+            "dispute_threshold" => self.dispute_threshold.to_string(),
+            _ => bail!("Config key '{}' not recognized", key)
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "currency" => self.currency = value.to_string(),
+            "starting_coins" => self.starting_coins = value.parse()?,
+            "income" => self.income = value.parse()?,
+            "interval" => {
+                let interval: u64 = value.parse()?;
+                if !(1..=MAX_INTERVAL).contains(&interval) {
+                    bail!("interval must be between 1 and {} hours", MAX_INTERVAL);
+                }
+                self.interval = interval;
+            },
+            "bet_amounts" => {
+                let amounts = parse_bet_amounts(value.split(|c: char| c == ',' || c.is_whitespace()).filter(|amount| !amount.is_empty()))?;
+                if amounts.is_empty() {
+                    bail!("expecting at least one amount, like '10% 50% 100%'");
+                }
+                self.bet_amounts = amounts;
+            },
+            "bet_policy" => self.bet_policy = BetPolicy::try_from(value)?,
+            "rake" => {
+                let rake: u32 = value.trim_end_matches('%').parse()?;
+                if rake > 100 {
+                    bail!("rake is a percentage, it can't be over 100");
+                }
+                self.rake = rake;
+            },
+            "no_winner" => self.no_winner = NoWinnerPolicy::try_from(value)?,
+            "give_cap" => self.give_cap = value.parse()?,
+            "give_confirm" => self.give_confirm = value.parse()?,
+            "compact" => self.compact = value.parse()?,
+            "manager_role" => self.manager_role = parse_role(value)?,
+            "creator_role" => self.creator_role = parse_role(value)?,
+            "author_bets" => self.author_bets = AuthorPolicy::try_from(value)?,
+            "dispute_window" => self.dispute_window = value.trim_end_matches("min").trim().parse()?,
+            "dispute_threshold" => {
+                let threshold: u32 = value.parse()?;
+                if threshold == 0 {
+                    bail!("at least 1 dispute is needed to hold a payout");
+                }
+                self.dispute_threshold = threshold;
+            },
+            _ => bail!("Config key '{}' not recognized", key)
         }
+        Ok(())
     }
 }
 
 lazy_static! {
-    pub static ref config: Config = Config::from(confy::load_path("./config.toml").unwrap());
+    pub static ref config: Config = Config::from(confy::load_path("./config.toml").expect("couldn't read config.toml"))
+        .expect("invalid config.toml");
 }
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use itertools::Itertools;
+use log::{info, warn};
+use serenity::{
+    all::{CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage},
+    http::Http, model::{application::{ButtonStyle, ComponentInteraction, InteractionResponseFlags}, prelude::ChannelId}, 
+    prelude::Context
+};
+use serenity_utils::{Button, CommandUtil, MessageBuilder};
+use crate::{
+    betting_bot::BettingBot, bot_db::PendingResolution, config::Config, front_utils::buttons_row, 
+    serialize_utils::BetAction
+};
+
+impl BettingBot {
+    pub async fn propose_resolution(
+        &self, ctx: &Context, command: &ComponentInteraction, bet_id: u64, winners: Vec<usize>, cfg: &Config
+    ) -> Result<()> {
+        let final_at = Utc::now().timestamp() + cfg.dispute_window as i64 * 60;
+        self.close_pending(&ctx.http, bet_id, "*Replaced by a new result*").await?;
+        let descs = self.outcome_descs(bet_id)?;
+        let winner_lines = winners.iter()
+            .map(|winner| format!("> {}", descs.get(*winner).cloned().unwrap_or_default()))
+            .join("\n");
+        let msg = command.response(
+            &ctx.http, 
+            MessageBuilder::new(format!(
+                "⏳ Pending {}\n{}\nPayouts are final <t:{}:R>, participants can dispute the result until then", 
+                if winners.len() > 1 { "winners" } else { "winner" }, winner_lines, final_at
+            )).buttons(vec![Button {
+                custom_id: BetAction::Dispute(bet_id).to_string(), 
+                label: "⚖️ Dispute".to_string(), style: ButtonStyle::Danger
+            }]), 
+            InteractionResponseFlags::default()
+        ).await?;
+        self.bot_db.set_pending_resolution(bet_id, &winners, final_at, msg.channel_id.get(), msg.id.get())?;
+        self.schedule_resolution(ctx.http.clone(), bet_id, final_at);
+        Ok(())
+    }
+
+    pub async fn finalize_resolution(&self, http: &Http, pending: PendingResolution) -> Result<()> {
+        let entry = self.bot_db.bet(pending.bet)?;
+        let cfg = self.bot_db.guild_config(entry.server)?;
+        // the dispute window and a bet manager may both try to settle it
+        if !self.bot_db.claim_pending_resolution(&pending)? {
+            bail!("the pending result of bet {} was already settled or replaced", pending.bet);
+        }
+        let announcement = self.pay_out(entry.server, pending.bet, &pending.winners, &cfg)?;
+        if let Err(why) = ChannelId::new(pending.channel).edit_message(
+            http, pending.message, EditMessage::new().content(&announcement).components(vec![])
+        ).await {
+            warn!(target: "betting-bot", "couldn't edit the pending result of bet {}: {}", pending.bet, why);
+        }
+        self.results_elsewhere(http, &entry, &announcement).await;
+        self.show_resolved(http, pending.bet, &pending.winners).await
+    }
+
+    pub async fn close_pending(&self, http: &Http, bet_id: u64, note: &str) -> Result<()> {
+        let Some(pending) = self.bot_db.pending_resolution(bet_id)? else {
+            return Ok(());
+        };
+        self.bot_db.remove_pending_resolution(bet_id)?;
+        self.pending_note(http, &pending, note).await
+    }
+
+    pub async fn pending_note(&self, http: &Http, pending: &PendingResolution, note: &str) -> Result<()> {
+        ChannelId::new(pending.channel).edit_message(
+            http, pending.message, EditMessage::new().content(note).components(vec![])
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn dispute_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let user_uuid = command.user.id.get();
+        let Some(pending) = self.bot_db.pending_resolution(bet_id)? else {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("This result is already final").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("dispute on a result that is not pending");
+        };
+        if self.bot_db.positions(bet_id, user_uuid)?.is_empty() {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Only the participants of the bet can dispute its result").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user without a position tried to dispute");
+        }
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        let disputes = self.bot_db.add_dispute(bet_id, user_uuid)?;
+        let participants = self.bot_db.wagers(bet_id)?.iter().map(|(_, user, _)| *user).unique().count();
+        // small bets can't hold more disputes than they have participants
+        let threshold = (cfg.dispute_threshold as usize).min(participants);
+        if pending.disputed || disputes < threshold {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new(format!("Your dispute was recorded ({}/{})", disputes, threshold)).ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            return Ok(());
+        }
+        self.bot_db.set_disputed(bet_id)?;
+        let descs = self.outcome_descs(bet_id)?;
+        info!(target: "betting-bot", "the result of bet {} is disputed by {} participants", bet_id, disputes);
+        command.create_response(
+            &ctx.http, 
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!(
+                        "⚠️ Disputed by {} participants, payouts are on hold\n{}\n\
+                        A bet manager has to confirm this result or pick the winners again on the bet", 
+                        disputes, pending.winners.iter()
+                            .map(|winner| format!("> {}", descs.get(*winner).cloned().unwrap_or_default()))
+                            .join("\n")
+                    ))
+                    .components(vec![buttons_row(vec![Button {
+                        custom_id: BetAction::DisputeConfirm(bet_id).to_string(), 
+                        label: "✅ Confirm".to_string(), style: ButtonStyle::Success
+                    }])])
+            )
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn dispute_confirm_action(&self, ctx: Context, command: &ComponentInteraction, bet_id: u64) -> Result<()> {
+        let server_uuid = command.guild_id.ok_or(anyhow!("action triggered outside server"))?.get();
+        let cfg = self.bot_db.guild_config(server_uuid)?;
+        if !self.is_manager(command, &cfg) {
+            command.response(
+                &ctx.http, 
+                MessageBuilder::new("Only a bet manager can settle a disputed result").ephemeral(true),
+                InteractionResponseFlags::EPHEMERAL
+            ).await?;
+            bail!("user tried to confirm a disputed result without being a bet manager");
+        }
+        let pending = self.bot_db.pending_resolution(bet_id)?.ok_or(anyhow!("no pending result for bet {}", bet_id))?;
+        command.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        self.finalize_resolution(&ctx.http, pending).await?;
+        info!(target: "betting-bot", "{} confirmed the disputed result of bet {}", command.user.id, bet_id);
+        Ok(())
+    }
+}
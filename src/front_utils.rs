@@ -1,10 +1,14 @@
 use betting::{Bet, Outcome};
 use betting::utils::lrm;
-use crate::config::config;
+use serenity::{all::{CreateActionRow, CreateButton, CreateEmbed}, model::application::ButtonStyle};
+use serenity_utils::Button;
+use crate::{bookmaker::FixedOdds, config::Config, policy::BetPolicy, serialize_utils::{BetAction, BetOutcome}};
 use itertools;
-use std::cmp::min;
+use std::{cmp::min, iter::once};
 
 const NUM_SUFFIX: [&str; 5] = ["", "K", "M", "B", "T"];
+// a message holds 5 rows of 5 buttons, the last row is for lock and abort
+pub const MAX_COMPACT_OUTCOMES: usize = 20;
 
 fn number_display<R>(x: R) -> String
 where
@@ -46,19 +50,19 @@ pub fn bet_stub(outcomes_desc: &Vec<String>) -> Bet {
     }
 }
 
-fn outcome_display(desc: &str, percent: u64, odd: f32, sum: u32, people: u32) -> String {
+fn outcome_stats(percent: u64, odd: f32, sum: u32, people: u32, currency: &str) -> String {
     format!(
-        "> {}\n` {: >3}%  | {: >6} 🏆  {: >4} {}  {: >4} 👥 `",
-        desc,
+        "` {: >3}%  | {: >6} 🏆  {: >4} {}  {: >4} 👥 `",
         percent,
         "1:".to_string() + &number_display(if odd.is_nan() { 1. } else { odd }),
         number_display(sum),
-        config.currency,
+        currency,
         number_display(people)
     )
 }
 
-pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
+/// The share, odds, amount and number of participants of every outcome
+fn outcomes_stats(bet_status: &Bet, cfg: &Config, fixed_odds: Option<&FixedOdds>) -> Vec<String> {
     let sums: Vec<u64> = bet_status
         .outcomes
         .iter()
@@ -74,7 +78,14 @@ pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
 
     let percents = lrm(100, &sums);
 
-    let odds: Vec<f32> = sums.iter().map(|sum| total as f32 / *sum as f32).collect();
+    let odds: Vec<f32> = match fixed_odds {
+        Some(fixed_odds) => fixed_odds.odds.iter().map(|odd| *odd as f32).collect(),
+        None => {
+            // what the winners actually share once the rake is taken
+            let net_total = total as f32 * (100 - cfg.rake) as f32 / 100.;
+            sums.iter().map(|sum| net_total / *sum as f32).collect()
+        }
+    };
 
     let peoples: Vec<usize> = bet_status
         .outcomes
@@ -82,18 +93,113 @@ pub fn outcomes_display(bet_status: &Bet) -> Vec<String> {
         .map(|outcome| outcome.wagers.len())
         .collect();
 
-    itertools::izip!(&bet_status.outcomes, percents, odds, sums, peoples)
-        .map(|(outcome, percent, odd, sum, people)| {
-            outcome_display(&outcome.desc, percent, odd, sum as u32, people as u32)
+    itertools::izip!(percents, odds, sums, peoples)
+        .map(|(percent, odd, sum, people)| {
+            outcome_stats(percent, odd, sum as u32, people as u32, &cfg.currency)
         })
         .collect()
 }
 
+pub fn outcomes_display(bet_status: &Bet, cfg: &Config, fixed_odds: Option<&FixedOdds>) -> Vec<String> {
+    bet_status.outcomes.iter().zip(outcomes_stats(bet_status, cfg, fixed_odds))
+        .map(|(outcome, stats)| format!("> {}\n{}", outcome.desc, stats))
+        .collect()
+}
+
+/// The whole bet in one embed with a field per outcome, winners are marked once resolved
+pub fn bet_embed(bet_status: &Bet, cfg: &Config, fixed_odds: Option<&FixedOdds>, winners: &[usize]) -> CreateEmbed {
+    bet_status.outcomes.iter().zip(outcomes_stats(bet_status, cfg, fixed_odds)).enumerate().fold(
+        CreateEmbed::new(),
+        |embed, (i, (outcome, stats))| embed.field(
+            format!("{}#{} {}", if winners.contains(&i) { "🏆 " } else { "" }, i + 1, shorten(&outcome.desc, 200)), 
+            stats, false
+        )
+    )
+}
+
+/// One button per outcome to open the bet modal, with the lock and abort buttons on the last row
+pub fn compact_bet_components(bet_id: u64, outcomes: &[String]) -> Vec<CreateActionRow> {
+    let mut rows: Vec<CreateActionRow> = outcomes.chunks(5).enumerate().map(|(row, chunk)| 
+        CreateActionRow::Buttons(chunk.iter().enumerate().map(|(col, desc)| {
+            let outcome_id = row * 5 + col;
+            CreateButton::new(BetAction::BetClick(BetOutcome { bet_id, outcome_id }).to_string())
+                .label(format!("#{} {}", outcome_id + 1, shorten(desc, 70)))
+                .style(ButtonStyle::Primary)
+        }).collect())
+    ).collect();
+    rows.push(CreateActionRow::Buttons(vec![
+        CreateButton::new(BetAction::Lock().to_string()).label("🔒 Lock".to_string()).style(ButtonStyle::Secondary),
+        CreateButton::new(BetAction::Abort().to_string()).label("🚫 Abort".to_string()).style(ButtonStyle::Secondary)
+    ]));
+    rows
+}
+
+/// The content of the bet message, with the lock countdown if the bet locks automatically 
+/// and the policy if it's not the usual one option only
+pub fn bet_header(desc: &str, lock_at: Option<i64>, policy: BetPolicy) -> String {
+    let mut header = desc.to_string();
+    if policy != BetPolicy::Single {
+        header += &format!("\n🔀 {}", policy.label());
+    }
+    if let Some(timestamp) = lock_at {
+        header += &format!("\n🔒 Locks <t:{}:R>", timestamp);
+    }
+    header
+}
+
+/// Who backs a fixed-odds bet and up to how much, appended to the bet header
+pub fn fixed_odds_line(author: u64, fixed_odds: &FixedOdds, currency: &str) -> String {
+    format!("\n📒 Fixed odds, <@{}> covers up to {} {}", author, fixed_odds.escrow, currency)
+}
+
+/// What the user has on every option of the bet, ex: "#1 100 💵 · #3 50 💵"
+pub fn positions_display(bet: &Bet, user: u64, currency: &str) -> String {
+    bet.outcomes.iter().enumerate().filter_map(|(i, outcome)| {
+        let amount: u64 = outcome.wagers.iter().filter(|(u, _)| *u == user).map(|(_, a)| a).sum();
+        if amount > 0 { Some(format!("#{} {} {}", i + 1, amount, currency)) } else { None }
+    }).collect::<Vec<String>>().join(" · ")
+}
+
+pub fn buttons_row(buttons: Vec<Button>) -> CreateActionRow {
+    CreateActionRow::Buttons(buttons.into_iter().map(|button| 
+        CreateButton::new(button.custom_id).label(button.label).style(button.style)
+    ).collect())
+}
+
+pub fn outcome_buttons(bet_outcome: &BetOutcome, cfg: &Config) -> Vec<Button> {
+    cfg.bet_amounts.iter().map(|amount| Button {
+        custom_id: BetAction::QuickBet(bet_outcome.clone(), *amount).to_string(),
+        label: amount.label(&cfg.currency), style: ButtonStyle::Primary
+    }).chain(once(Button {
+        custom_id: BetAction::BetClick(bet_outcome.clone()).to_string(),
+        label: "✏️ Custom".to_string(), style: ButtonStyle::Secondary
+    })).collect()
+}
+
 pub fn shorten(text: &str, length: usize) -> String {
     let res = text.split_once("\n").and_then(|(first, _)| Some(first)).unwrap_or(text);
-    if res.len() > length {
-        res.split_at(length-1).0.to_owned() + "…"
+    // counted in chars, slicing bytes could split an emoji or an accent
+    if res.chars().count() > length {
+        res.chars().take(length-1).collect::<String>() + "…"
     } else {
         res.to_owned()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shorten;
+
+    #[test]
+    fn shorten_keeps_short_text() {
+        assert_eq!(shorten("Who wins?", 20), "Who wins?");
+        assert_eq!(shorten("Who wins?\nDetails", 20), "Who wins?");
+    }
+
+    #[test]
+    fn shorten_cuts_on_chars() {
+        assert_eq!(shorten("abcdef", 4), "abc…");
+        assert_eq!(shorten("éééééé", 4), "ééé…");
+        assert_eq!(shorten("🎲🎲🎲🎲🎲", 3), "🎲🎲…");
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use anyhow::{bail, Result};
+use betting::{AccountStatus, Bets};
+use itertools::Itertools;
+use serenity::{
+    all::{CreateActionRow, CreateButton, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage},
+    http::Http, model::{application::ButtonStyle, prelude::ChannelId}
+};
+use crate::{bot_db::BotDB, config::Config, serialize_utils::BetAction};
+pub const NET_WORTH: &str = "net_worth";
+pub const BALANCE: &str = "balance";
+pub const PROFIT: &str = "profit";
+pub const WIN_RATE: &str = "win_rate";
+pub const PAGE_SIZE: usize = 10;
+pub const PERMANENT_HEADER: &str = "*🏆 Leaderboard (updated live)*";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaderboardSort {
+    NetWorth,
+    Balance,
+    Profit,
+    WinRate
+}
+
+impl LeaderboardSort {
+    pub const ALL: [LeaderboardSort; 4] = [
+        LeaderboardSort::NetWorth, LeaderboardSort::Balance, LeaderboardSort::Profit, LeaderboardSort::WinRate
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            LeaderboardSort::NetWorth => "Net worth",
+            LeaderboardSort::Balance => "Free balance",
+            LeaderboardSort::Profit => "Betting profit",
+            LeaderboardSort::WinRate => "Win rate"
+        }
+    }
+}
+
+impl ToString for LeaderboardSort {
+    fn to_string(&self) -> String {
+        match self {
+            LeaderboardSort::NetWorth => NET_WORTH,
+            LeaderboardSort::Balance => BALANCE,
+            LeaderboardSort::Profit => PROFIT,
+            LeaderboardSort::WinRate => WIN_RATE
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for LeaderboardSort {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            NET_WORTH => LeaderboardSort::NetWorth,
+            BALANCE => LeaderboardSort::Balance,
+            PROFIT => LeaderboardSort::Profit,
+            WIN_RATE => LeaderboardSort::WinRate,
+            _ => bail!("Leaderboard sort '{}' not recognized", value)
+        })
+    }
+}
+
+struct Entry {
+    account: AccountStatus,
+    profit: i64,
+    // (bets won, bets entered)
+    wins: (u64, u64)
+}
+
+impl Entry {
+    fn win_rate(&self) -> f64 {
+        if self.wins.1 == 0 { 0. } else { self.wins.0 as f64 / self.wins.1 as f64 }
+    }
+
+    fn display(&self, sort: LeaderboardSort) -> String {
+        let acc = &self.account;
+        match sort {
+            LeaderboardSort::NetWorth | LeaderboardSort::Balance => format!("{}  ({})   <@{}>", acc.balance, acc.in_bet, acc.user),
+            LeaderboardSort::Profit => format!("{:+}   <@{}>", self.profit, acc.user),
+            LeaderboardSort::WinRate => format!(
+                "{:.0}%  ({}/{})   <@{}>", 100. * self.win_rate(), self.wins.0, self.wins.1, acc.user
+            )
+        }
+    }
+}
+
+/// The accounts of the server ranked by the sort, best first
+fn ranking(bets: &Bets, bot_db: &BotDB, server: u64, sort: LeaderboardSort) -> Result<Vec<Entry>> {
+    let wins: HashMap<u64, (u64, u64)> = bot_db.win_counts(server)?.into_iter()
+        .map(|(user, entered, won)| (user, (won, entered))).collect();
+    let profits: HashMap<u64, i64> = bot_db.betting_profits(server)?.into_iter().collect();
+    let mut entries: Vec<Entry> = bets.accounts(server)?.into_iter().map(|account| Entry {
+        // the stakes still in bets aren't lost yet
+        profit: profits.get(&account.user).copied().unwrap_or(0) + account.in_bet as i64,
+        wins: *wins.get(&account.user).unwrap_or(&(0, 0)),
+        account
+    }).collect();
+    // sort by the criteria first and net worth to tie break
+    entries.sort_by(|a, b| {
+        let net_worth = |e: &Entry| e.account.balance + e.account.in_bet;
+        match sort {
+            LeaderboardSort::NetWorth => (net_worth(a), a.account.balance).cmp(&(net_worth(b), b.account.balance)),
+            LeaderboardSort::Balance => (a.account.balance, net_worth(a)).cmp(&(b.account.balance, net_worth(b))),
+            LeaderboardSort::Profit => (a.profit, net_worth(a)).cmp(&(b.profit, net_worth(b))),
+            LeaderboardSort::WinRate => a.win_rate().total_cmp(&b.win_rate())
+                .then(a.wins.1.cmp(&b.wins.1))
+                .then(net_worth(a).cmp(&net_worth(b)))
+        }.reverse()
+    });
+    Ok(entries)
+}
+
+fn header(sort: LeaderboardSort, cfg: &Config) -> String {
+    match sort {
+        LeaderboardSort::NetWorth | LeaderboardSort::Balance => format!("{}  ({} in bet)   user", cfg.currency, cfg.currency),
+        LeaderboardSort::Profit => format!("{} won betting   user", cfg.currency),
+        LeaderboardSort::WinRate => "won  (won/entered)   user".to_string()
+    }
+}
+
+pub fn page_count(bets: &Bets, server: u64) -> Result<usize> {
+    Ok(((bets.accounts(server)?.len() + PAGE_SIZE - 1) / PAGE_SIZE).max(1))
+}
+
+/// A page of the leaderboard, with the rank of the caller at the bottom if they're not on it
+pub fn leaderboard_display(
+    bets: &Bets, bot_db: &BotDB, server: u64, sort: LeaderboardSort, page: usize, caller: Option<u64>
+) -> Result<String> {
+    let cfg = bot_db.guild_config(server)?;
+    let entries = ranking(bets, bot_db, server, sort)?;
+    let pages = ((entries.len() + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+    let mut msg = format!("**{}** (page {}/{})\n{}\n", sort.label(), page + 1, pages, header(sort, &cfg))
+        + &entries.iter().enumerate().skip(page * PAGE_SIZE).take(PAGE_SIZE)
+            .map(|(i, entry)| format!("`#{}` {}", i + 1, entry.display(sort)))
+            .join("\n");
+    if let Some(user) = caller {
+        match entries.iter().position(|entry| entry.account.user == user) {
+            Some(i) if i / PAGE_SIZE != page => msg += &format!("\n...\n`#{}` {}", i + 1, entries[i].display(sort)),
+            None => msg += "\n...\n*You don't have an account yet*",
+            _ => {}
+        }
+    }
+    Ok(msg)
+}
+
+pub fn leaderboard_components(sort: LeaderboardSort, page: usize, pages: usize) -> Vec<CreateActionRow> {
+    vec![
+        CreateActionRow::SelectMenu(CreateSelectMenu::new(
+            BetAction::LeaderboardSort().to_string(),
+            CreateSelectMenuKind::String { options: LeaderboardSort::ALL.iter().map(|option|
+                CreateSelectMenuOption::new(option.label(), option.to_string()).default_selection(*option == sort)
+            ).collect() }
+        )),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(BetAction::Leaderboard(sort, page.saturating_sub(1)).to_string())
+                .label("◀ Previous").style(ButtonStyle::Secondary).disabled(page == 0),
+            CreateButton::new(BetAction::Leaderboard(sort, page + 1).to_string())
+                .label("Next ▶").style(ButtonStyle::Secondary).disabled(page + 1 >= pages)
+        ])
+    ]
+}
+
+/// Edits the permanent leaderboard of the server if there's one, forgets it if the message is gone
+pub async fn leaderboard_refresh(http: &Http, bets: &Bets, bot_db: &BotDB, server: u64) -> Result<()> {
+    let Some((channel, message)) = bot_db.leaderboard(server)? else {
+        return Ok(());
+    };
+    let content = format!(
+        "{}\n{}", PERMANENT_HEADER, leaderboard_display(bets, bot_db, server, LeaderboardSort::NetWorth, 0, None)?
+    );
+    match ChannelId::new(channel).edit_message(http, message, EditMessage::new().content(content)).await {
+        Err(serenity::Error::Http(why)) if why.status_code().map(|code| code.as_u16()) == Some(404) => {
+            bot_db.remove_leaderboard(message)?;
+        },
+        res => { res?; }
+    }
+    Ok(())
+}
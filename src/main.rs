@@ -1,9 +1,19 @@
+mod amount;
 mod config;
+mod dispute;
 mod serialize_utils;
 mod front_utils;
 mod betting_bot;
 mod betting_commands;
 mod betting_events;
+mod bookmaker;
+mod bot_db;
+mod leaderboard;
+mod policy;
+mod render;
+mod resync;
+mod scheduler;
+mod stats;
 use betting_bot::BettingBot;
 use env_logger;
 use log::{warn, LevelFilter};
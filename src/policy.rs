@@ -0,0 +1,126 @@
+use anyhow::bail;
+pub const SINGLE: &str = "single";
+pub const SWITCH: &str = "switch";
+pub const SPREAD: &str = "spread";
+
+/// What a user can do once they have money on an outcome of the bet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetPolicy {
+    /// only bet on the first option picked
+    Single,
+    /// betting on another option moves the whole stake there
+    Switch,
+    /// bet on as many options as wanted
+    Spread
+}
+
+impl BetPolicy {
+    pub const ALL: [BetPolicy; 3] = [BetPolicy::Single, BetPolicy::Switch, BetPolicy::Spread];
+
+    pub fn label(&self) -> &str {
+        match self {
+            BetPolicy::Single => "One option only",
+            BetPolicy::Switch => "Switching option allowed",
+            BetPolicy::Spread => "Betting on several options allowed"
+        }
+    }
+}
+
+impl ToString for BetPolicy {
+    fn to_string(&self) -> String {
+        match self {
+            BetPolicy::Single => SINGLE,
+            BetPolicy::Switch => SWITCH,
+            BetPolicy::Spread => SPREAD
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for BetPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            SINGLE => BetPolicy::Single,
+            SWITCH => BetPolicy::Switch,
+            SPREAD => BetPolicy::Spread,
+            _ => bail!("Bet policy '{}' not recognized, expected single, switch or spread", value)
+        })
+    }
+}
+
+pub const REFUND: &str = "refund";
+pub const JACKPOT: &str = "jackpot";
+pub const TREASURY: &str = "treasury";
+
+/// What happens to the pool when nobody bet on the winning outcome
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoWinnerPolicy {
+    /// everyone gets their wager back
+    Refund,
+    /// the pool is added to the winnings of the next bet
+    Jackpot,
+    /// the pool goes to the server treasury
+    Treasury
+}
+
+impl ToString for NoWinnerPolicy {
+    fn to_string(&self) -> String {
+        match self {
+            NoWinnerPolicy::Refund => REFUND,
+            NoWinnerPolicy::Jackpot => JACKPOT,
+            NoWinnerPolicy::Treasury => TREASURY
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for NoWinnerPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            REFUND => NoWinnerPolicy::Refund,
+            JACKPOT => NoWinnerPolicy::Jackpot,
+            TREASURY => NoWinnerPolicy::Treasury,
+            _ => bail!("No winner policy '{}' not recognized, expected refund, jackpot or treasury", value)
+        })
+    }
+}
+
+pub const FREE: &str = "free";
+pub const NO_BETTING: &str = "no_betting";
+pub const OTHER_RESOLVER: &str = "other_resolver";
+
+/// What the author of a bet can do on their own bet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthorPolicy {
+    /// bet and resolve like anyone
+    Free,
+    /// no betting on their own bets
+    NoBetting,
+    /// betting is allowed but another manager has to resolve the bet then
+    OtherResolver
+}
+
+impl ToString for AuthorPolicy {
+    fn to_string(&self) -> String {
+        match self {
+            AuthorPolicy::Free => FREE,
+            AuthorPolicy::NoBetting => NO_BETTING,
+            AuthorPolicy::OtherResolver => OTHER_RESOLVER
+        }.to_string()
+    }
+}
+
+impl TryFrom<&str> for AuthorPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
+            FREE => AuthorPolicy::Free,
+            NO_BETTING => AuthorPolicy::NoBetting,
+            OTHER_RESOLVER => AuthorPolicy::OtherResolver,
+            _ => bail!("Author policy '{}' not recognized, expected free, no_betting or other_resolver", value)
+        })
+    }
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashMap, sync::{Arc, MutexGuard}, time::Duration};
+use anyhow::{anyhow, Result};
+use betting::Bet;
+use log::warn;
+use serenity::{all::EditMessage, http::Http, model::prelude::ChannelId};
+use crate::{
+    betting_bot::BettingBot, bot_db::BetStatus, front_utils::{bet_embed, outcomes_display}, 
+    serialize_utils::BetOutcome
+};
+// wagers coming in during this window are shown in a single pass
+const RENDER_DELAY: Duration = Duration::from_millis(1500);
+// failed renders in a row before waiting for the next wager to try again, the messages may be gone for good
+const RENDER_ATTEMPTS: u32 = 5;
+
+struct Render {
+    channel_id: ChannelId,
+    latest: Option<Bet>,
+    shown: Vec<String>,
+    // a single task renders the bet at a time so that an older state never overwrites a newer one
+    running: bool
+}
+
+#[derive(Default)]
+pub struct Renders(HashMap<u64, Render>);
+
+impl BettingBot {
+    fn renders(&self) -> Result<MutexGuard<Renders>> {
+        self.renders.lock().map_err(|_| anyhow!("renders mutex is poisoned"))
+    }
+
+    pub fn outcomes_update(&self, http: Arc<Http>, channel_id: ChannelId, bet: Bet) -> Result<()> {
+        let bet_id = bet.bet;
+        let mut renders = self.renders()?;
+        let render = renders.0.entry(bet_id).or_insert(Render { channel_id, latest: None, shown: Vec::new(), running: false });
+        render.latest = Some(bet);
+        if render.running {
+            return Ok(());
+        }
+        render.running = true;
+        let bot = self.clone();
+        tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                tokio::time::sleep(RENDER_DELAY).await;
+                match bot.render_latest(&http, bet_id).await {
+                    Ok(true) => failures = 0,
+                    Ok(false) => break,
+                    Err(why) => {
+                        warn!(target: "betting-bot", "couldn't update the outcomes of bet {}: {:?}", bet_id, why);
+                        failures += 1;
+                        if failures >= RENDER_ATTEMPTS {
+                            bot.stop_render(bet_id);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub fn forget_render(&self, bet_id: u64) -> Result<()> {
+        self.renders()?.0.remove(&bet_id);
+        Ok(())
+    }
+
+    fn stop_render(&self, bet_id: u64) {
+        if let Ok(mut renders) = self.renders() {
+            if let Some(render) = renders.0.get_mut(&bet_id) {
+                render.latest = None;
+                render.running = false;
+            }
+        }
+    }
+
+    async fn render_latest(&self, http: &Http, bet_id: u64) -> Result<bool> {
+        let (channel_id, bet, shown) = {
+            let mut renders = self.renders()?;
+            let Some(render) = renders.0.get_mut(&bet_id) else {
+                return Ok(false);
+            };
+            let Some(bet) = render.latest.take() else {
+                render.running = false;
+                return Ok(false);
+            };
+            // taken until the edits went through, a failed render shows everything again next time
+            (render.channel_id, bet, std::mem::take(&mut render.shown))
+        };
+        match self.render_bet(http, channel_id, &bet, &shown).await {
+            Ok(Some(displays)) => {
+                if let Some(render) = self.renders()?.0.get_mut(&bet_id) {
+                    render.shown = displays;
+                }
+                self.leaderboard_update(http, bet.server).await;
+                Ok(true)
+            },
+            Ok(None) => Ok(false),
+            Err(why) => {
+                if let Some(render) = self.renders()?.0.get_mut(&bet_id) {
+                    if render.latest.is_none() {
+                        render.latest = Some(bet);
+                    }
+                }
+                Err(why)
+            }
+        }
+    }
+
+    async fn render_bet(&self, http: &Http, channel_id: ChannelId, bet: &Bet, shown: &[String]) -> Result<Option<Vec<String>>> {
+        let bet_id = bet.bet;
+        // the bet may have been resolved or aborted during the window, its messages are final
+        if !matches!(self.bot_db.bet(bet_id)?.status, BetStatus::Open | BetStatus::Locked) {
+            self.forget_render(bet_id)?;
+            return Ok(None);
+        }
+        let cfg = self.bot_db.guild_config(bet.server)?;
+        let fixed_odds = self.bot_db.fixed_odds(bet_id)?;
+        let displays = outcomes_display(bet, &cfg, fixed_odds.as_ref());
+        if self.bot_db.is_compact(bet_id)? {
+            if displays != shown {
+                channel_id.edit_message(http, bet_id, EditMessage::new().embed(bet_embed(bet, &cfg, fixed_odds.as_ref(), &[]))).await?;
+            }
+        } else {
+            for (i, display) in displays.iter().enumerate() {
+                if shown.get(i) == Some(display) {
+                    continue;
+                }
+                let msg_id = self.msg_map.get(BetOutcome { bet_id, outcome_id: i })?;
+                channel_id.edit_message(http, msg_id, EditMessage::new().content(display)).await?;
+            }
+        }
+        Ok(Some(displays))
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use betting::{Bet, Outcome};
+use log::{info, warn};
+use serenity::{
+    all::{ActionRowComponent, Button, ButtonKind, EditMessage}, http::Http, model::prelude::{ChannelId, GuildId}, prelude::Context
+};
+use crate::{
+    betting_bot::BettingBot, betting_commands::{locked_bet_components, open_bet_buttons, resolve_components}, 
+    bot_db::{BetEntry, BetStatus}, front_utils::{bet_embed, buttons_row, compact_bet_components, outcome_buttons, outcomes_display}, 
+    serialize_utils::{BetAction, BetOutcome}
+};
+const MISSING_MESSAGES: &str = "messages missing, abort it with /abort";
+
+pub fn is_not_found(why: &serenity::Error) -> bool {
+    matches!(why, serenity::Error::Http(why) if why.status_code().map(|code| code.as_u16()) == Some(404))
+}
+
+impl BettingBot {
+    /// Brings the messages of every active bet back in line with the DB, 
+    /// they may have been edited or deleted while the bot was offline
+    pub async fn resync_bets(&self, ctx: &Context) {
+        for guild_id in ctx.cache.guilds() {
+            if let Err(why) = self.track_untracked_bets(ctx, guild_id).await {
+                warn!(target: "betting-bot", "couldn't track the untracked bets of {}: {:?}", guild_id, why);
+            }
+            let entries = match self.bot_db.bets(guild_id.get(), &[BetStatus::Open, BetStatus::Locked]) {
+                Ok(entries) => entries,
+                Err(why) => {
+                    warn!(target: "betting-bot", "couldn't get the active bets of {}: {:?}", guild_id, why);
+                    continue;
+                }
+            };
+            for entry in entries {
+                match self.resync_bet(&ctx.http, &entry).await {
+                    Ok(true) => if let Err(why) = self.bot_db.clear_issue(entry.bet) {
+                        warn!(target: "betting-bot", "couldn't unflag bet {}: {:?}", entry.bet, why);
+                    },
+                    Ok(false) => {
+                        warn!(target: "betting-bot", "the messages of bet {} are missing", entry.bet);
+                        if let Err(why) = self.bot_db.set_issue(entry.bet, &format!("{} {}", MISSING_MESSAGES, entry.bet)) {
+                            warn!(target: "betting-bot", "couldn't flag bet {}: {:?}", entry.bet, why);
+                        }
+                    },
+                    Err(why) => warn!(target: "betting-bot", "couldn't resync bet {}: {:?}", entry.bet, why)
+                }
+            }
+        }
+        info!(target: "betting-bot", "bet messages resynced");
+    }
+
+    /// Starts tracking the active bets only the `betting` crate knows about, 
+    /// their channel isn't known so the bet message is looked for in every channel of the server
+    async fn track_untracked_bets(&self, ctx: &Context, guild_id: GuildId) -> Result<()> {
+        let server = guild_id.get();
+        let tracked = self.bot_db.bets(server, &BetStatus::ALL)?;
+        let untracked: Vec<u64> = self.bets.active_bets(server)?.into_iter()
+            .filter(|bet| !tracked.iter().any(|entry| entry.bet == *bet))
+            .collect();
+        if untracked.is_empty() {
+            return Ok(());
+        }
+        let channels = guild_id.channels(&ctx.http).await?;
+        let lock = BetAction::Lock().to_string();
+        for bet in untracked {
+            let mut found = None;
+            for channel_id in channels.keys() {
+                if let Ok(message) = channel_id.message(&ctx.http, bet).await {
+                    found = Some(message);
+                    break;
+                }
+            }
+            let Some(message) = found else {
+                warn!(target: "betting-bot", "couldn't find the message of untracked bet {}", bet);
+                continue;
+            };
+            let is_open = message.components.iter().flat_map(|row| &row.components).any(|component| matches!(
+                component, ActionRowComponent::Button(Button { data: ButtonKind::NonLink { custom_id, .. }, .. }) if *custom_id == lock
+            ));
+            self.bot_db.add_bet(bet, server, message.channel_id.get(), self.bets.get_info(bet)?.author)?;
+            if !is_open {
+                self.bot_db.set_status(bet, BetStatus::Locked)?;
+            }
+            info!(target: "betting-bot", "started tracking bet {} in {}", bet, message.channel_id);
+        }
+        Ok(())
+    }
+
+    /// The bet as tracked by the bot, None for bets from before the outcomes were recorded
+    pub fn tracked_bet(&self, entry: &BetEntry) -> Result<Option<Bet>> {
+        let descs = self.bot_db.outcomes(entry.bet)?;
+        if descs.is_empty() {
+            return Ok(None);
+        }
+        let wagers = self.bot_db.wagers(entry.bet)?;
+        Ok(Some(Bet {
+            bet: entry.bet,
+            desc: self.bets.get_info(entry.bet)?.desc,
+            outcomes: descs.into_iter().enumerate().map(|(i, desc)| Outcome {
+                desc,
+                wagers: wagers.iter().filter(|(outcome, _, _)| *outcome == i).map(|(_, user, amount)| (*user, *amount)).collect()
+            }).collect(),
+            server: entry.server,
+            author: entry.author,
+            is_open: entry.status == BetStatus::Open
+        }))
+    }
+
+    /// Re-renders the messages of the bet, returns false if some of them are gone
+    async fn resync_bet(&self, http: &Http, entry: &BetEntry) -> Result<bool> {
+        let channel_id = ChannelId::new(entry.channel);
+        if self.bot_db.is_compact(entry.bet)? {
+            return self.resync_compact_bet(http, channel_id, entry).await;
+        }
+        let bet_components = match entry.status {
+            BetStatus::Open => vec![buttons_row(open_bet_buttons())],
+            _ => locked_bet_components(&self.outcome_descs(entry.bet)?)
+        };
+        match channel_id.edit_message(http, entry.bet, EditMessage::new().components(bet_components)).await {
+            Err(why) if is_not_found(&why) => return Ok(false),
+            res => { res?; }
+        }
+        let cfg = self.bot_db.guild_config(entry.server)?;
+        let displays = match self.tracked_bet(entry)? {
+            Some(bet) => outcomes_display(&bet, &cfg, self.bot_db.fixed_odds(entry.bet)?.as_ref()).into_iter().map(Some).collect(),
+            None => vec![None; self.bets.outcomes_of_bet(entry.bet)?.into_iter().count()]
+        };
+        for (outcome_id, display) in displays.into_iter().enumerate() {
+            let outcome = BetOutcome { bet_id: entry.bet, outcome_id };
+            let Ok(msg_id) = self.msg_map.get(outcome.clone()) else {
+                return Ok(false);
+            };
+            let mut edit = EditMessage::new().components(match entry.status {
+                BetStatus::Open => vec![buttons_row(outcome_buttons(&outcome, &cfg))],
+                _ => resolve_components(outcome)
+            });
+            if let Some(display) = display {
+                edit = edit.content(display);
+            }
+            match channel_id.edit_message(http, msg_id, edit).await {
+                Err(why) if is_not_found(&why) => return Ok(false),
+                res => { res?; }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Compact bets only have the bet message, with the outcomes in its embed
+    async fn resync_compact_bet(&self, http: &Http, channel_id: ChannelId, entry: &BetEntry) -> Result<bool> {
+        let descs = self.outcome_descs(entry.bet)?;
+        let mut edit = EditMessage::new().components(match entry.status {
+            BetStatus::Open => compact_bet_components(entry.bet, &descs),
+            _ => locked_bet_components(&descs)
+        });
+        if let Some(bet) = self.tracked_bet(entry)? {
+            let cfg = self.bot_db.guild_config(entry.server)?;
+            edit = edit.embed(bet_embed(&bet, &cfg, self.bot_db.fixed_odds(entry.bet)?.as_ref(), &[]));
+        }
+        match channel_id.edit_message(http, entry.bet, edit).await {
+            Err(why) if is_not_found(&why) => Ok(false),
+            res => res.map(|_| true).map_err(Into::into)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{sync::Arc, time::Duration};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serenity::{all::EditMessage, http::Http, model::prelude::ChannelId, prelude::Context};
+use crate::{betting_bot::BettingBot, betting_commands::locked_bet_components, bot_db::{BetStatus, TxKind}};
+const HOUR: i64 = 3600;
+
+impl BettingBot {
+    /// Locks the bet at the given unix timestamp, right away if it's already past
+    pub fn schedule_lock(&self, http: Arc<Http>, bet_id: u64, lock_at: i64) {
+        let bot = self.clone();
+        tokio::spawn(async move {
+            let delay = (lock_at - Utc::now().timestamp()).max(0) as u64;
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            if let Err(why) = bot.auto_lock(&http, bet_id, lock_at).await {
+                warn!(target: "betting-bot", "couldn't auto lock bet {}: {:?}", bet_id, why);
+            }
+        });
+    }
+
+    async fn auto_lock(&self, http: &Http, bet_id: u64, lock_at: i64) -> Result<()> {
+        // the bet may have been locked, aborted or rescheduled in the meantime
+        if !self.bot_db.auto_locks()?.contains(&(bet_id, lock_at)) {
+            return Ok(());
+        }
+        self.bot_db.remove_auto_lock(bet_id)?;
+        let entry = self.bot_db.bet(bet_id)?;
+        if entry.status != BetStatus::Open {
+            return Ok(());
+        }
+        self.bets.lock_bet(bet_id)?;
+        self.bot_db.set_status(bet_id, BetStatus::Locked)?;
+        let channel_id = ChannelId::new(entry.channel);
+        channel_id.edit_message(http, bet_id,
+            EditMessage::new().content(self.locked_header(bet_id)?).components(locked_bet_components(&self.outcome_descs(bet_id)?))
+        ).await?;
+        self.lock_outcomes(http, channel_id, bet_id).await?;
+        info!(target: "betting-bot", "auto locked bet {}", bet_id);
+        Ok(())
+    }
+
+    /// Pays the income of every server at the wall-clock boundaries of its interval
+    pub fn schedule_income(&self, ctx: Context) {
+        let bot = self.clone();
+        tokio::spawn(async move {
+            loop {
+                for guild_id in ctx.cache.guilds() {
+                    if let Err(why) = bot.income_catch_up(&ctx.http, guild_id.get()).await {
+                        warn!(target: "betting-bot", "couldn't distribute income in {}: {:?}", guild_id, why);
+                    }
+                }
+                // intervals are in hours so every boundary falls on the hour
+                let delay = HOUR - Utc::now().timestamp().rem_euclid(HOUR);
+                tokio::time::sleep(Duration::from_secs(delay as u64)).await;
+            }
+        });
+    }
+
+    /// Pays the income once for every boundary crossed since the last payment, 
+    /// so that restarts and downtime don't skip any
+    async fn income_catch_up(&self, http: &Http, server: u64) -> Result<()> {
+        let cfg = self.bot_db.guild_config(server)?;
+        let period = i64::try_from(cfg.interval).ok().and_then(|interval| interval.checked_mul(HOUR))
+            .filter(|period| *period > 0)
+            .ok_or(anyhow!("invalid income interval of {} hours", cfg.interval))?;
+        let boundary = Utc::now().timestamp().div_euclid(period) * period;
+        let Some(last_paid) = self.bot_db.last_income(server)? else {
+            // first time we see this server, start counting from here
+            self.bot_db.set_last_income(server, boundary)?;
+            return Ok(());
+        };
+        let missed = boundary.div_euclid(period) - last_paid.div_euclid(period);
+        if missed <= 0 {
+            return Ok(());
+        }
+        // saved before paying, skipping a payment on a crash is better than paying twice
+        self.bot_db.set_last_income(server, boundary)?;
+        let income = cfg.income as i64 * missed;
+        for account in self.bets.accounts(server)? {
+            self.bets.change_balance(server, account.user, income)?;
+            self.bot_db.add_transaction(server, account.user, income, TxKind::Income, None)?;
+        }
+        info!(target: "betting-bot", "distributed income of {} ({}x{}) in {}", income, missed, cfg.income, server);
+        self.leaderboard_update(http, server).await;
+        Ok(())
+    }
+
+    /// Reschedules the locks that were pending when the bot stopped
+    pub fn schedule_pending_locks(&self, http: Arc<Http>) -> Result<()> {
+        for (bet_id, lock_at) in self.bot_db.auto_locks()? {
+            self.schedule_lock(http.clone(), bet_id, lock_at);
+        }
+        Ok(())
+    }
+
+    /// Pays out the pending resolution when its dispute window closes, right away if it's already past
+    pub fn schedule_resolution(&self, http: Arc<Http>, bet_id: u64, final_at: i64) {
+        let bot = self.clone();
+        tokio::spawn(async move {
+            let delay = (final_at - Utc::now().timestamp()).max(0) as u64;
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            if let Err(why) = bot.auto_resolve(&http, bet_id, final_at).await {
+                warn!(target: "betting-bot", "couldn't finalize the resolution of bet {}: {:?}", bet_id, why);
+            }
+        });
+    }
+
+    async fn auto_resolve(&self, http: &Http, bet_id: u64, final_at: i64) -> Result<()> {
+        // the result may have been disputed, replaced or settled in the meantime
+        let Some(pending) = self.bot_db.pending_resolution(bet_id)? else {
+            return Ok(());
+        };
+        if pending.final_at != final_at || pending.disputed || self.bot_db.bet(bet_id)?.status != BetStatus::Locked {
+            return Ok(());
+        }
+        self.finalize_resolution(http, pending).await?;
+        info!(target: "betting-bot", "finalized the resolution of bet {}", bet_id);
+        Ok(())
+    }
+
+    /// Reschedules the resolutions that were waiting for their dispute window when the bot stopped
+    pub fn schedule_pending_resolutions(&self, http: Arc<Http>) -> Result<()> {
+        for pending in self.bot_db.pending_resolutions()? {
+            self.schedule_resolution(http.clone(), pending.bet, pending.final_at);
+        }
+        Ok(())
+    }
+}
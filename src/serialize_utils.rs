@@ -1,19 +1,42 @@
 use anyhow::{anyhow, bail};
 use itertools::Itertools;
 use rusqlite::{ToSql, types::{ToSqlOutput, Value}};
+use crate::{amount::Amount, leaderboard::LeaderboardSort};
 pub const LOCK: &str = "lock";
 pub const BET_CLICK: &str = "bet_click";
+pub const QUICK_BET: &str = "quick_bet";
 pub const RESOLVE: &str = "resolve";
+pub const RESOLVE_SELECT: &str = "resolve_select";
 pub const ABORT: &str = "abort";
 pub const BET_ORDER: &str = "bet_order";
+pub const RESET: &str = "reset";
+pub const RESET_CANCEL: &str = "reset_cancel";
+pub const LEADERBOARD: &str = "leaderboard";
+pub const LEADERBOARD_SORT: &str = "leaderboard_sort";
+pub const HISTORY: &str = "history";
+pub const GIVE: &str = "give";
+pub const GIVE_CANCEL: &str = "give_cancel";
+pub const DISPUTE: &str = "dispute";
+pub const DISPUTE_CONFIRM: &str = "dispute_confirm";
 
 #[derive(Debug)]
 pub enum BetAction {
     Lock(),
     Abort(),
     BetClick(BetOutcome),
+    QuickBet(BetOutcome, Amount),
     Resolve(BetOutcome),
-    BetOrder()
+    ResolveSelect(),
+    BetOrder(),
+    Reset(),
+    ResetCancel(),
+    Leaderboard(LeaderboardSort, usize),
+    LeaderboardSort(),
+    History(usize),
+    Give(u64),
+    GiveCancel(u64),
+    Dispute(u64),
+    DisputeConfirm(u64)
 }
 
 impl ToString for BetAction {
@@ -21,9 +44,22 @@ impl ToString for BetAction {
         match self {
             BetAction::Lock() => format!("{}-", LOCK),
             BetAction::BetClick(bet_outcome) => format!("{}-{}", BET_CLICK, bet_outcome.to_string()),
+            BetAction::QuickBet(bet_outcome, amount) => format!(
+                "{}-{}-{}", QUICK_BET, amount.to_string(), bet_outcome.to_string()
+            ),
             BetAction::Resolve(bet_outcome) => format!("{}-{}", RESOLVE, bet_outcome.to_string()),
+            BetAction::ResolveSelect() => format!("{}-", RESOLVE_SELECT),
             BetAction::Abort() => format!("{}-", ABORT),
-            BetAction::BetOrder() => format!("{}-", BET_ORDER)
+            BetAction::BetOrder() => format!("{}-", BET_ORDER),
+            BetAction::Reset() => format!("{}-", RESET),
+            BetAction::ResetCancel() => format!("{}-", RESET_CANCEL),
+            BetAction::Leaderboard(sort, page) => format!("{}-{}-{}", LEADERBOARD, sort.to_string(), page),
+            BetAction::LeaderboardSort() => format!("{}-", LEADERBOARD_SORT),
+            BetAction::History(page) => format!("{}-{}", HISTORY, page),
+            BetAction::Give(pending_id) => format!("{}-{}", GIVE, pending_id),
+            BetAction::GiveCancel(pending_id) => format!("{}-{}", GIVE_CANCEL, pending_id),
+            BetAction::Dispute(bet_id) => format!("{}-{}", DISPUTE, bet_id),
+            BetAction::DisputeConfirm(bet_id) => format!("{}-{}", DISPUTE_CONFIRM, bet_id)
         }
     }
 }
@@ -38,9 +74,30 @@ impl TryFrom<String> for BetAction {
         Ok(match action {
             LOCK => BetAction::Lock(),
             BET_CLICK => BetAction::BetClick(BetOutcome::try_from(data)?),
+            QUICK_BET => {
+                let (amount, bet_outcome) = data.splitn(2, "-").collect_tuple().ok_or(
+                    anyhow!("'{}' is not a quick bet. Expecting <amount>-<bet_id>-<outcome_id>", data)
+                )?;
+                BetAction::QuickBet(BetOutcome::try_from(bet_outcome)?, Amount::try_from(amount)?)
+            },
             RESOLVE => BetAction::Resolve(BetOutcome::try_from(data)?),
+            RESOLVE_SELECT => BetAction::ResolveSelect(),
             ABORT => BetAction::Abort(),
             BET_ORDER => BetAction::BetOrder(),
+            RESET => BetAction::Reset(),
+            RESET_CANCEL => BetAction::ResetCancel(),
+            LEADERBOARD => {
+                let (sort, page) = data.splitn(2, "-").collect_tuple().ok_or(
+                    anyhow!("'{}' is not a leaderboard page. Expecting <sort>-<page>", data)
+                )?;
+                BetAction::Leaderboard(LeaderboardSort::try_from(sort)?, page.parse()?)
+            },
+            LEADERBOARD_SORT => BetAction::LeaderboardSort(),
+            HISTORY => BetAction::History(data.parse()?),
+            GIVE => BetAction::Give(data.parse()?),
+            GIVE_CANCEL => BetAction::GiveCancel(data.parse()?),
+            DISPUTE => BetAction::Dispute(data.parse()?),
+            DISPUTE_CONFIRM => BetAction::DisputeConfirm(data.parse()?),
             _ => bail!("Bet action '{}' not recognized", action)
         })
     }
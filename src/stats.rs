@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use crate::{bot_db::BetResult, config::Config};
+
+pub struct Stats {
+    pub entered: usize,
+    pub won: usize,
+    pub wagered: u64,
+    pub profit: i64,
+    pub biggest_win: i64,
+    pub biggest_loss: i64,
+    pub longest_streak: usize,
+    pub favourite_creator: Option<(u64, usize)>
+}
+
+impl Stats {
+    pub fn from(results: &[BetResult]) -> Self {
+        let nets: Vec<i64> = results.iter().map(|res| res.payout as i64 - res.wagered as i64).collect();
+        let mut longest_streak = 0;
+        let mut streak = 0;
+        for res in results {
+            streak = if res.won { streak + 1 } else { 0 };
+            longest_streak = longest_streak.max(streak);
+        }
+        let mut creators: HashMap<u64, usize> = HashMap::new();
+        for res in results {
+            *creators.entry(res.author).or_default() += 1;
+        }
+        Stats {
+            entered: results.len(),
+            won: results.iter().filter(|res| res.won).count(),
+            wagered: results.iter().map(|res| res.wagered).sum(),
+            profit: nets.iter().sum(),
+            biggest_win: nets.iter().copied().filter(|net| *net > 0).max().unwrap_or(0),
+            biggest_loss: nets.iter().copied().filter(|net| *net < 0).min().unwrap_or(0),
+            longest_streak,
+            // tie break on the creator id so that the result is stable
+            favourite_creator: creators.into_iter().max_by_key(|(author, count)| (*count, *author))
+        }
+    }
+
+    pub fn display(&self, user: u64, cfg: &Config) -> String {
+        if self.entered == 0 {
+            return format!("<@{}> hasn't taken part in any resolved bet yet", user);
+        }
+        let win_rate = 100. * self.won as f64 / self.entered as f64;
+        let roi = 100. * self.profit as f64 / self.wagered.max(1) as f64;
+        let favourite = match self.favourite_creator {
+            Some((author, count)) => format!("<@{}> ({} bets)", author, count),
+            None => "-".to_string()
+        };
+        format!(
+            "**Stats of** <@{}>\n\
+            Bets entered: {}\n\
+            Win rate: {:.0}% ({}/{})\n\
+            Total wagered: {} {}\n\
+            Net profit: {:+} {}\n\
+            ROI: {:+.1}%\n\
+            Biggest win: {:+} {}\n\
+            Biggest loss: {:+} {}\n\
+            Longest win streak: {}\n\
+            Favourite bet creator: {}",
+            user, self.entered, win_rate, self.won, self.entered,
+            self.wagered, cfg.currency, self.profit, cfg.currency, roi,
+            self.biggest_win, cfg.currency, self.biggest_loss, cfg.currency,
+            self.longest_streak, favourite
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot_db::BetResult;
+    use super::Stats;
+
+    fn result(author: u64, wagered: u64, payout: u64) -> BetResult {
+        BetResult { author, wagered, payout, won: payout > 0 }
+    }
+
+    #[test]
+    fn no_results() {
+        let stats = Stats::from(&[]);
+        assert_eq!(stats.entered, 0);
+        assert_eq!(stats.profit, 0);
+        assert_eq!(stats.longest_streak, 0);
+        assert!(stats.favourite_creator.is_none());
+    }
+
+    #[test]
+    fn aggregates_results() {
+        let stats = Stats::from(&[
+            result(10, 50, 120), result(20, 30, 0), result(10, 20, 25), 
+            result(10, 40, 60), result(20, 100, 0)
+        ]);
+        assert_eq!(stats.entered, 5);
+        assert_eq!(stats.won, 3);
+        assert_eq!(stats.wagered, 240);
+        assert_eq!(stats.profit, -35);
+        assert_eq!(stats.biggest_win, 70);
+        assert_eq!(stats.biggest_loss, -100);
+        assert_eq!(stats.longest_streak, 2);
+        assert_eq!(stats.favourite_creator, Some((10, 3)));
+    }
+
+    #[test]
+    fn favourite_creator_ties_on_the_id() {
+        let stats = Stats::from(&[result(10, 10, 0), result(20, 10, 0)]);
+        assert_eq!(stats.favourite_creator, Some((20, 1)));
+    }
+}